@@ -0,0 +1,153 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use tokio_util::sync::WaitForCancellationFutureOwned;
+
+use super::Shutdown;
+
+/// Extends every [`Future`] with [`ShutdownFutureExt::with_shutdown`].
+pub trait ShutdownFutureExt: Future + Sized {
+    /// Resolves to `Some(output)` if the future completes before `shutdown`
+    /// fires, else resolves to `None` as soon as `shutdown` fires.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use toolbox::shutdown::{Shutdown, ShutdownFutureExt};
+    ///
+    /// let shutdown = Shutdown::new();
+    /// assert_eq!(async { 1 }.with_shutdown(&shutdown).await, Some(1));
+    ///
+    /// shutdown.shutdown();
+    /// assert_eq!(futures::future::pending::<()>().with_shutdown(&shutdown).await, None);
+    /// # })
+    /// ```
+    fn with_shutdown(self, shutdown: &Shutdown) -> WithShutdown<Self> {
+        WithShutdown {
+            future: Box::pin(self),
+            cancelled: Box::pin(shutdown.token.clone().cancelled_owned()),
+        }
+    }
+}
+
+impl<F> ShutdownFutureExt for F where F: Future {}
+
+/// Extends every [`Stream`] with [`ShutdownStreamExt::take_until_shutdown`].
+pub trait ShutdownStreamExt: Stream + Sized {
+    /// Yields items from the stream until `shutdown` fires, at which point the
+    /// stream ends.
+    ///
+    /// Any item being produced when `shutdown` fires is dropped, see
+    /// `IntervalStream::with_shutdown` for a variant that finishes in-progress
+    /// work first.
+    fn take_until_shutdown(self, shutdown: &Shutdown) -> UntilShutdown<Self> {
+        UntilShutdown {
+            stream: Box::pin(self),
+            cancelled: Some(Box::pin(shutdown.token.clone().cancelled_owned())),
+        }
+    }
+}
+
+impl<S> ShutdownStreamExt for S where S: Stream {}
+
+/// Future returned by [`ShutdownFutureExt::with_shutdown`].
+#[must_use = "futures do nothing unless polled"]
+pub struct WithShutdown<F> {
+    future: Pin<Box<F>>,
+    cancelled: Pin<Box<WaitForCancellationFutureOwned>>,
+}
+
+impl<F> Future for WithShutdown<F>
+where
+    F: Future,
+{
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.cancelled.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+
+        self.future.as_mut().poll(cx).map(Some)
+    }
+}
+
+/// Stream returned by [`ShutdownStreamExt::take_until_shutdown`].
+#[must_use = "streams do nothing unless polled"]
+pub struct UntilShutdown<S> {
+    stream: Pin<Box<S>>,
+    cancelled: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
+}
+
+impl<S> Stream for UntilShutdown<S>
+where
+    S: Stream,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(cancelled) = &mut self.cancelled else {
+            return Poll::Ready(None);
+        };
+        if cancelled.as_mut().poll(cx).is_ready() {
+            self.cancelled = None;
+
+            return Poll::Ready(None);
+        }
+
+        self.stream.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[test]
+    fn future_completes_before_shutdown() {
+        tokio_test::block_on(async {
+            let shutdown = Shutdown::new();
+
+            assert_eq!(async { 5 }.with_shutdown(&shutdown).await, Some(5));
+        });
+    }
+
+    #[test]
+    fn future_cancelled_by_shutdown() {
+        tokio_test::block_on(async {
+            let shutdown = Shutdown::new();
+            let trigger = shutdown.clone();
+            tokio::spawn(async move { trigger.shutdown() });
+
+            let output = futures::future::pending::<()>()
+                .with_shutdown(&shutdown)
+                .await;
+            assert_eq!(output, None);
+        });
+    }
+
+    #[test]
+    fn stream_ends_on_shutdown() {
+        tokio_test::block_on(async {
+            let shutdown = Shutdown::new();
+            let mut stream = futures::stream::iter(0..)
+                .then(|i| async move {
+                    tokio::task::yield_now().await;
+                    i
+                })
+                .take_until_shutdown(&shutdown);
+
+            assert_eq!(stream.next().await, Some(0));
+            assert_eq!(stream.next().await, Some(1));
+
+            shutdown.shutdown();
+            assert_eq!(stream.next().await, None);
+            assert_eq!(stream.next().await, None);
+        });
+    }
+}
@@ -0,0 +1,308 @@
+/// Cancellation-aware wrappers for futures & streams.
+mod combinators;
+/// Ordered shutdown phases driven by a [`Shutdown`].
+mod phases;
+
+use std::fmt;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+pub use combinators::*;
+pub use phases::*;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+
+/// How often blocked threads check whether [`Shutdown::token`] was cancelled
+/// directly, which doesn't notify them.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    pub token: CancellationToken,
+    pub shutdown: Arc<AtomicBool>,
+    state: Arc<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    reason: OnceLock<ShutdownReason>,
+    lock: Mutex<()>,
+    condvar: Condvar,
+}
+
+impl Shutdown {
+    #[allow(clippy::new_without_default)]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            token: CancellationToken::new(),
+            shutdown: Arc::new(AtomicBool::new(false)),
+            state: Arc::default(),
+        }
+    }
+
+    /// Triggers shutdown with [`ShutdownReason::Requested`].
+    pub fn shutdown(&self) {
+        self.shutdown_with(ShutdownReason::Requested);
+    }
+
+    /// Triggers shutdown, recording `reason` if no earlier reason was recorded.
+    ///
+    /// Only the first reason is kept, subsequent calls still trigger shutdown
+    /// (which is idempotent) but their reason is discarded.
+    pub fn shutdown_with(&self, reason: ShutdownReason) {
+        let _ = self.state.reason.set(reason);
+
+        // Set the flag under the lock so a concurrent `wait` cannot miss the
+        // notification.
+        {
+            let _lock = self
+                .state
+                .lock
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            self.shutdown.store(true, Ordering::Relaxed);
+        }
+        self.state.condvar.notify_all();
+        self.token.cancel();
+    }
+
+    #[must_use]
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
+    }
+
+    pub fn cancelled(&self) -> WaitForCancellationFuture<'_> {
+        self.token.cancelled()
+    }
+
+    /// Blocks the current thread until shutdown is triggered.
+    ///
+    /// Intended for plain OS threads, async tasks should use
+    /// [`Shutdown::cancelled`] instead. Cancelling [`Shutdown::token`] directly
+    /// (or via a parent token) also counts as shutdown, though blocked threads
+    /// may take up to 50ms to notice.
+    pub fn wait(&self) {
+        let _ = self.wait_timeout(Duration::MAX);
+    }
+
+    /// Blocks the current thread until shutdown is triggered or `timeout`
+    /// elapses.
+    ///
+    /// Returns `true` if shutdown was triggered. See [`Shutdown::wait`] for
+    /// caveats.
+    #[must_use]
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now().checked_add(timeout);
+        let mut lock = self
+            .state
+            .lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while !self.is_triggered() {
+            let remaining = deadline.map_or(CANCEL_POLL_INTERVAL, |deadline| {
+                deadline.saturating_duration_since(Instant::now())
+            });
+            if remaining.is_zero() {
+                break;
+            }
+
+            (lock, _) = self
+                .state
+                .condvar
+                .wait_timeout(lock, remaining.min(CANCEL_POLL_INTERVAL))
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+
+        self.is_triggered()
+    }
+
+    /// Whether shutdown was triggered, either via [`Shutdown::shutdown_with`]
+    /// or by cancelling [`Shutdown::token`] directly.
+    fn is_triggered(&self) -> bool {
+        self.is_shutdown() || self.token.is_cancelled()
+    }
+
+    /// The reason passed to the first shutdown call, if shutdown has been
+    /// triggered.
+    #[must_use]
+    pub fn reason(&self) -> Option<&ShutdownReason> {
+        self.state.reason.get()
+    }
+
+    /// The process exit code implied by the shutdown reason.
+    ///
+    /// If shutdown was never triggered, [`ExitCode::SUCCESS`] is returned.
+    #[must_use]
+    pub fn exit_code(&self) -> ExitCode {
+        self.reason()
+            .map_or(ExitCode::SUCCESS, |reason| ExitCode::from(reason.exit_code()))
+    }
+
+    /// Logs a structured summary of why the process is shutting down.
+    #[cfg(feature = "tracing")]
+    pub fn log_summary(&self) {
+        match self.reason() {
+            None => tracing::warn!(reason = "none", "Shutdown summary"),
+            Some(reason @ ShutdownReason::Requested) => {
+                tracing::info!(
+                    reason = reason.kind(),
+                    exit_code = reason.exit_code(),
+                    "Shutdown summary"
+                );
+            }
+            Some(reason @ ShutdownReason::Signal(signal)) => tracing::info!(
+                reason = reason.kind(),
+                signal,
+                exit_code = reason.exit_code(),
+                "Shutdown summary"
+            ),
+            Some(reason @ ShutdownReason::Error(err)) => tracing::error!(
+                reason = reason.kind(),
+                err = %err,
+                exit_code = reason.exit_code(),
+                "Shutdown summary"
+            ),
+            Some(reason @ ShutdownReason::Panic { message, location }) => tracing::error!(
+                reason = reason.kind(),
+                message = %message,
+                location = location.as_deref(),
+                exit_code = reason.exit_code(),
+                "Shutdown summary"
+            ),
+        }
+    }
+}
+
+/// Why shutdown was triggered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// Shutdown was requested as part of normal operation.
+    Requested,
+    /// The process received the given signal number.
+    Signal(i32),
+    /// A task failed with a fatal error.
+    Error(String),
+    /// A thread panicked.
+    Panic { message: String, location: Option<String> },
+}
+
+impl ShutdownReason {
+    /// A short, stable name for the reason, suitable for structured logging.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ShutdownReason::Requested => "requested",
+            ShutdownReason::Signal(_) => "signal",
+            ShutdownReason::Error(_) => "error",
+            ShutdownReason::Panic { .. } => "panic",
+        }
+    }
+
+    /// The conventional exit code for this reason.
+    ///
+    /// Signals follow the shell convention of `128 + signal` & panics use
+    /// `101` to match the exit code of a panicking `main`.
+    #[must_use]
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ShutdownReason::Requested => 0,
+            ShutdownReason::Signal(signal) => {
+                u8::try_from(128 + (*signal).clamp(0, 127)).unwrap_or(u8::MAX)
+            }
+            ShutdownReason::Error(_) => 1,
+            ShutdownReason::Panic { .. } => 101,
+        }
+    }
+}
+
+impl fmt::Display for ShutdownReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownReason::Requested => write!(f, "Shutdown requested"),
+            ShutdownReason::Signal(signal) => write!(f, "Received signal; signal={signal}"),
+            ShutdownReason::Error(err) => write!(f, "Fatal error; err={err}"),
+            ShutdownReason::Panic { message, location: Some(location) } => {
+                write!(f, "Panicked; message={message}; location={location}")
+            }
+            ShutdownReason::Panic { message, location: None } => {
+                write!(f, "Panicked; message={message}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_defaults_to_requested() {
+        let shutdown = Shutdown::new();
+        assert_eq!(shutdown.reason(), None);
+
+        shutdown.shutdown();
+        assert!(shutdown.is_shutdown());
+        assert!(shutdown.token.is_cancelled());
+        assert_eq!(shutdown.reason(), Some(&ShutdownReason::Requested));
+        assert_eq!(shutdown.exit_code(), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn first_reason_wins() {
+        let shutdown = Shutdown::new();
+        let clone = shutdown.clone();
+
+        clone.shutdown_with(ShutdownReason::Error("disk full".to_string()));
+        shutdown.shutdown_with(ShutdownReason::Signal(15));
+        shutdown.shutdown();
+
+        assert_eq!(shutdown.reason(), Some(&ShutdownReason::Error("disk full".to_string())));
+    }
+
+    #[test]
+    fn wait_blocks_until_shutdown() {
+        let shutdown = Shutdown::new();
+        let waiters: Vec<_> = (0..4)
+            .map(|_| {
+                let shutdown = shutdown.clone();
+
+                std::thread::spawn(move || shutdown.wait())
+            })
+            .collect();
+
+        assert!(!shutdown.wait_timeout(Duration::from_millis(10)));
+        shutdown.shutdown();
+        for waiter in waiters {
+            waiter.join().unwrap();
+        }
+        assert!(shutdown.wait_timeout(Duration::ZERO));
+    }
+
+    #[test]
+    fn wait_wakes_on_token_cancellation() {
+        let parent = CancellationToken::new();
+        let mut shutdown = Shutdown::new();
+        shutdown.token = parent.child_token();
+        let waiter = {
+            let shutdown = shutdown.clone();
+
+            std::thread::spawn(move || shutdown.wait())
+        };
+
+        assert!(!shutdown.wait_timeout(Duration::from_millis(10)));
+        parent.cancel();
+        waiter.join().unwrap();
+        assert!(shutdown.wait_timeout(Duration::ZERO));
+        assert_eq!(shutdown.reason(), None);
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(ShutdownReason::Requested.exit_code(), 0);
+        assert_eq!(ShutdownReason::Error(String::new()).exit_code(), 1);
+        assert_eq!(ShutdownReason::Signal(2).exit_code(), 130);
+        assert_eq!(ShutdownReason::Signal(15).exit_code(), 143);
+    }
+}
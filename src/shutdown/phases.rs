@@ -0,0 +1,246 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+use tokio_util::task::task_tracker::{TaskTrackerToken, TrackedFuture};
+use tokio_util::task::TaskTracker;
+
+use super::Shutdown;
+
+/// Splits shutdown into ordered phases.
+///
+/// Once the underlying [`Shutdown`] fires, each phase is cancelled in the
+/// order it was registered. A phase is only cancelled after every task tracked
+/// by the previous phase has completed or the previous phase's timeout has
+/// elapsed.
+///
+/// # Example
+///
+/// ```rust
+/// # tokio_test::block_on(async {
+/// use std::time::Duration;
+///
+/// use toolbox::shutdown::{Shutdown, ShutdownPhases};
+///
+/// let shutdown = Shutdown::new();
+/// let mut phases = ShutdownPhases::new(shutdown.clone());
+/// let ingress = phases.phase("ingress", Duration::from_secs(5));
+/// let storage = phases.phase("storage", Duration::from_secs(30));
+///
+/// ingress.spawn({
+///     let ingress = ingress.clone();
+///     async move { ingress.cancelled().await }
+/// });
+/// storage.spawn({
+///     let storage = storage.clone();
+///     async move { storage.cancelled().await }
+/// });
+///
+/// shutdown.shutdown();
+/// let outcomes = phases.run().await;
+/// assert!(outcomes.iter().all(|outcome| !outcome.timed_out));
+/// # })
+/// ```
+#[derive(Debug)]
+pub struct ShutdownPhases {
+    shutdown: Shutdown,
+    phases: Vec<ShutdownPhase>,
+}
+
+impl ShutdownPhases {
+    #[must_use]
+    pub fn new(shutdown: Shutdown) -> Self {
+        ShutdownPhases { shutdown, phases: Vec::default() }
+    }
+
+    /// Registers a new phase that runs after all previously registered phases.
+    ///
+    /// `timeout` bounds how long the phase's tasks are waited on before moving
+    /// on to the next phase.
+    pub fn phase(&mut self, name: impl Into<String>, timeout: Duration) -> ShutdownPhase {
+        let phase = ShutdownPhase {
+            name: name.into(),
+            timeout,
+            token: CancellationToken::new(),
+            tracker: TaskTracker::new(),
+        };
+        self.phases.push(phase.clone());
+
+        phase
+    }
+
+    /// Waits for shutdown and then drives each phase to completion in order.
+    pub async fn run(self) -> Vec<PhaseOutcome> {
+        self.shutdown.cancelled().await;
+
+        let mut outcomes = Vec::with_capacity(self.phases.len());
+        for phase in self.phases {
+            let start = Instant::now();
+
+            phase.tracker.close();
+            phase.token.cancel();
+            let timed_out = tokio::time::timeout(phase.timeout, phase.tracker.wait())
+                .await
+                .is_err();
+
+            let outcome = PhaseOutcome {
+                name: phase.name,
+                timed_out,
+                remaining: phase.tracker.len(),
+                elapsed: start.elapsed(),
+            };
+            #[cfg(feature = "tracing")]
+            if outcome.timed_out {
+                tracing::warn!(
+                    phase = outcome.name,
+                    remaining = outcome.remaining,
+                    elapsed = ?outcome.elapsed,
+                    "Shutdown phase timed out"
+                );
+            } else {
+                tracing::info!(
+                    phase = outcome.name,
+                    elapsed = ?outcome.elapsed,
+                    "Shutdown phase complete"
+                );
+            }
+            outcomes.push(outcome);
+        }
+
+        outcomes
+    }
+}
+
+/// A single phase registered with [`ShutdownPhases`].
+///
+/// Tasks belonging to the phase should be tracked via [`ShutdownPhase::spawn`],
+/// [`ShutdownPhase::track`] or [`ShutdownPhase::guard`] and should exit once
+/// [`ShutdownPhase::cancelled`] resolves.
+#[derive(Debug, Clone)]
+pub struct ShutdownPhase {
+    name: String,
+    timeout: Duration,
+    token: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl ShutdownPhase {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolves once this phase begins.
+    pub fn cancelled(&self) -> WaitForCancellationFuture<'_> {
+        self.token.cancelled()
+    }
+
+    /// Spawns a task on the current runtime that this phase waits on.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tracker.spawn(task)
+    }
+
+    /// Wraps a future so that this phase waits on it.
+    pub fn track<F: Future>(&self, future: F) -> TrackedFuture<F> {
+        self.tracker.track_future(future)
+    }
+
+    /// Returns a token that this phase waits on until it is dropped.
+    ///
+    /// Useful for work that is not a future, e.g. a [`std::thread`].
+    pub fn guard(&self) -> TaskTrackerToken {
+        self.tracker.token()
+    }
+}
+
+/// The result of running a single [`ShutdownPhase`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhaseOutcome {
+    pub name: String,
+    /// Whether the phase's timeout elapsed before all tasks completed.
+    pub timed_out: bool,
+    /// The number of tasks still running when the phase ended.
+    pub remaining: usize,
+    pub elapsed: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn phases_run_in_order() {
+        tokio_test::block_on(async {
+            let shutdown = Shutdown::new();
+            let mut phases = ShutdownPhases::new(shutdown.clone());
+            let ingress = phases.phase("ingress", Duration::from_secs(5));
+            let workers = phases.phase("workers", Duration::from_secs(5));
+            let storage = phases.phase("storage", Duration::from_secs(5));
+
+            // Each task records the order it exits in and asserts later phases
+            // have not yet begun.
+            let order = Arc::new(Mutex::new(Vec::default()));
+            for (phase, later) in [
+                (ingress.clone(), vec![workers.clone(), storage.clone()]),
+                (workers.clone(), vec![storage.clone()]),
+                (storage.clone(), vec![]),
+            ] {
+                let order = order.clone();
+                phase.clone().spawn(async move {
+                    phase.cancelled().await;
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    assert!(later.iter().all(|phase| !phase.is_cancelled()));
+                    order.lock().unwrap().push(phase.name().to_string());
+                });
+            }
+
+            shutdown.shutdown();
+            let outcomes = phases.run().await;
+
+            assert_eq!(*order.lock().unwrap(), ["ingress", "workers", "storage"]);
+            assert_eq!(
+                outcomes
+                    .iter()
+                    .map(|outcome| outcome.name.as_str())
+                    .collect::<Vec<_>>(),
+                ["ingress", "workers", "storage"]
+            );
+            assert!(outcomes
+                .iter()
+                .all(|outcome| !outcome.timed_out && outcome.remaining == 0));
+        });
+    }
+
+    #[test]
+    fn phase_times_out() {
+        tokio_test::block_on(async {
+            let shutdown = Shutdown::new();
+            let mut phases = ShutdownPhases::new(shutdown.clone());
+            let stuck = phases.phase("stuck", Duration::from_millis(10));
+            let next = phases.phase("next", Duration::from_secs(5));
+
+            // Never exits.
+            let _guard = stuck.guard();
+
+            shutdown.shutdown();
+            let outcomes = phases.run().await;
+
+            assert!(outcomes[0].timed_out);
+            assert_eq!(outcomes[0].remaining, 1);
+            assert!(!outcomes[1].timed_out);
+            assert!(next.is_cancelled());
+        });
+    }
+}
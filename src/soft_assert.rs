@@ -1,45 +1,838 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Environment variable that selects the [`Mode`], either `soft` or `panic`.
+pub const MODE_ENV_VAR: &str = "SOFT_ASSERT_MODE";
+
+static HANDLER: RwLock<ViolationHandler> = RwLock::new(ViolationHandler::Stderr);
+static REGISTRY: Mutex<Vec<&'static Callsite>> = Mutex::new(Vec::new());
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+thread_local! {
+    static THREAD_MODE: Cell<Option<Mode>> = const { Cell::new(None) };
+    static CAPTURED: RefCell<Option<Vec<CapturedViolation>>> = const { RefCell::new(None) };
+}
+
+/// A single [`soft_assert!`](crate::soft_assert!) violation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation<'a> {
+    pub file: &'static str,
+    pub line: u32,
+    pub message: Option<&'a str>,
+}
+
+/// Determines where [`soft_assert!`](crate::soft_assert!) violations are
+/// reported.
+#[derive(Clone, Default)]
+pub enum ViolationHandler {
+    /// Print violations to stderr.
+    #[default]
+    Stderr,
+    /// Log violations as `WARN` events with `file`, `line` & `msg` fields.
+    #[cfg(feature = "tracing")]
+    Tracing,
+    /// Pass violations to a user provided closure.
+    Custom(Arc<dyn Fn(&Violation<'_>) + Send + Sync>),
+}
+
+impl ViolationHandler {
+    pub fn custom<F>(handler: F) -> Self
+    where
+        F: Fn(&Violation<'_>) + Send + Sync + 'static,
+    {
+        ViolationHandler::Custom(Arc::new(handler))
+    }
+
+    fn handle(&self, violation: &Violation<'_>) {
+        match self {
+            ViolationHandler::Stderr => match violation.message {
+                Some(msg) => eprintln!(
+                    "Soft assert violated; msg={msg}; file={}; line={}",
+                    violation.file, violation.line
+                ),
+                None => eprintln!(
+                    "Soft assert violated; file={}; line={}",
+                    violation.file, violation.line
+                ),
+            },
+            #[cfg(feature = "tracing")]
+            ViolationHandler::Tracing => tracing::warn!(
+                file = violation.file,
+                line = violation.line,
+                msg = violation.message,
+                "Soft assert violated"
+            ),
+            ViolationHandler::Custom(handler) => handler(violation),
+        }
+    }
+}
+
+impl fmt::Debug for ViolationHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViolationHandler::Stderr => write!(f, "Stderr"),
+            #[cfg(feature = "tracing")]
+            ViolationHandler::Tracing => write!(f, "Tracing"),
+            ViolationHandler::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// Replaces the process wide handler used to report
+/// [`soft_assert!`](crate::soft_assert!) violations.
+pub fn set_violation_handler(handler: ViolationHandler) {
+    *HANDLER.write().unwrap_or_else(PoisonError::into_inner) = handler;
+}
+
+/// Snapshot of a single callsite's violation history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViolationRecord {
+    pub file: &'static str,
+    pub line: u32,
+    /// The stringified condition that was violated.
+    pub condition: &'static str,
+    /// The most recently reported message, if any.
+    pub message: Option<String>,
+    /// How many times the condition has been violated, including violations
+    /// that were not reported.
+    pub count: u64,
+    pub first_seen: SystemTime,
+    pub last_seen: SystemTime,
+}
+
+/// Returns a snapshot of every callsite that has been violated at least once.
+///
+/// Covers [`soft_assert!`](crate::soft_assert!) & (with the `tracing`
+/// feature) `assert_once!`. Useful for dumping a summary on shutdown or
+/// exporting violation counts as metrics.
+#[must_use]
+pub fn violations() -> Vec<ViolationRecord> {
+    REGISTRY
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|callsite| callsite.snapshot())
+        .collect()
+}
+
+/// Resets every callsite as though it had never been violated.
+///
+/// Re-arms all callsites (so their next violation is reported again) & clears
+/// their counts. Primarily intended for tests.
+pub fn reset() {
+    let mut registry = REGISTRY.lock().unwrap_or_else(PoisonError::into_inner);
+    for callsite in registry.drain(..) {
+        callsite.reset();
+    }
+}
+
+/// Whether violations are reported (soft) or escalated to panics (hard).
+///
+/// Applies to [`soft_assert!`](crate::soft_assert!) & its variants as well as
+/// (with the `tracing` feature) `assert_once!` & `error_once!`. The mode is
+/// resolved in the following order:
+///
+/// 1. A thread local override set by [`with_mode`].
+/// 2. A process wide override set by [`set_mode`].
+/// 3. The [`MODE_ENV_VAR`] environment variable.
+/// 4. [`Mode::Panic`] if the `soft_assert_panic` feature is enabled or the
+///    assertion is compiled as part of `cfg(test)`, else [`Mode::Soft`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Soft,
+    Panic,
+}
+
+impl Mode {
+    fn from_u8(mode: u8) -> Option<Self> {
+        match mode {
+            1 => Some(Mode::Soft),
+            2 => Some(Mode::Panic),
+            _ => None,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Mode::Soft => 1,
+            Mode::Panic => 2,
+        }
+    }
+
+    fn resolve(test: bool) -> Self {
+        static ENV: OnceLock<Option<Mode>> = OnceLock::new();
+
+        THREAD_MODE
+            .get()
+            .or_else(|| Mode::from_u8(MODE.load(Ordering::Relaxed)))
+            .or_else(|| {
+                *ENV.get_or_init(|| match std::env::var(MODE_ENV_VAR).ok()?.as_str() {
+                    "soft" => Some(Mode::Soft),
+                    "panic" => Some(Mode::Panic),
+                    _ => None,
+                })
+            })
+            .unwrap_or(if test || cfg!(feature = "soft_assert_panic") {
+                Mode::Panic
+            } else {
+                Mode::Soft
+            })
+    }
+}
+
+/// Sets the process wide [`Mode`].
+pub fn set_mode(mode: Mode) {
+    MODE.store(mode.as_u8(), Ordering::Relaxed);
+}
+
+/// Runs `f` with the [`Mode`] overridden for the current thread.
+pub fn with_mode<F, R>(mode: Mode, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let _guard = ModeGuard(THREAD_MODE.replace(Some(mode)));
+
+    f()
+}
+
+/// A violation recorded by [`capture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedViolation {
+    pub file: &'static str,
+    pub line: u32,
+    pub condition: &'static str,
+    pub message: Option<String>,
+}
+
+/// Runs `f`, capturing every violation on the current thread instead of
+/// reporting or escalating it.
+///
+/// Unlike normal reporting, captured violations ignore the callsite's
+/// [`ReportPolicy`] so every violation is returned.
+///
+/// # Example
+///
+/// ```rust
+/// use toolbox::soft_assert;
+/// use toolbox::soft_assert::capture;
+///
+/// let violations = capture(|| soft_assert!(1 + 1 == 3, "bad math"));
+/// assert_eq!(violations.len(), 1);
+/// assert_eq!(violations[0].condition, "1 + 1 == 3");
+/// assert_eq!(violations[0].message.as_deref(), Some("bad math"));
+/// ```
+pub fn capture<F>(f: F) -> Vec<CapturedViolation>
+where
+    F: FnOnce(),
+{
+    let _guard = CaptureGuard(CAPTURED.replace(Some(Vec::default())));
+
+    f();
+
+    CAPTURED.take().unwrap_or_default()
+}
+
+/// Restores the previous thread local [`Mode`] on drop.
+struct ModeGuard(Option<Mode>);
+
+impl Drop for ModeGuard {
+    fn drop(&mut self) {
+        THREAD_MODE.set(self.0);
+    }
+}
+
+/// Restores the previous capture (if any) on drop.
+struct CaptureGuard(Option<Vec<CapturedViolation>>);
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        CAPTURED.set(self.0.take());
+    }
+}
+
+/// Determines which violations of a callsite are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportPolicy {
+    /// Report only the first violation.
+    Once,
+    /// Report a violation, then stay silent until the interval elapses.
+    Every(Duration),
+    /// Report the 1st, (N+1)th, (2N+1)th, ... violation.
+    EveryN(u64),
+}
+
+impl ReportPolicy {
+    /// Whether the occurrence after `previous` earlier occurrences should be
+    /// reported, claiming the report in `last_reported` for
+    /// [`ReportPolicy::Every`].
+    ///
+    /// `now` & `last_reported` are [`monotonic_nanos`], so stepping the wall
+    /// clock back can't silence a callsite.
+    pub(crate) fn should_report(self, previous: u64, now: u64, last_reported: &AtomicU64) -> bool {
+        match self {
+            ReportPolicy::Once => previous == 0,
+            ReportPolicy::EveryN(n) => previous % n.max(1) == 0,
+            ReportPolicy::Every(interval) => {
+                let interval = u64::try_from(interval.as_nanos()).unwrap_or(u64::MAX);
+                let last = last_reported.load(Ordering::Relaxed);
+
+                (last == 0 || now.saturating_sub(last) >= interval)
+                    && last_reported
+                        .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                        .is_ok()
+            }
+        }
+    }
+}
+
+/// Nanoseconds since the process started (or rather, since the first call),
+/// never `0` as that marks a callsite that was never reported.
+pub(crate) fn monotonic_nanos() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    let elapsed = START.get_or_init(Instant::now).elapsed().as_nanos();
+
+    u64::try_from(elapsed).unwrap_or(u64::MAX).max(1)
+}
+
+/// Nanoseconds since the unix epoch.
+fn now_nanos() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    u64::try_from(now).unwrap_or(u64::MAX)
+}
+
+/// Violation state for a single assertion callsite.
+///
+/// Each macro invocation owns a `static` callsite that registers itself with
+/// the global registry on its first violation.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct Callsite {
+    file: &'static str,
+    line: u32,
+    condition: &'static str,
+    registered: AtomicBool,
+    count: AtomicU64,
+    /// Nanoseconds since [`SystemTime::UNIX_EPOCH`].
+    first_seen: AtomicU64,
+    /// Nanoseconds since [`SystemTime::UNIX_EPOCH`].
+    last_seen: AtomicU64,
+    /// See [`monotonic_nanos`].
+    last_reported: AtomicU64,
+    message: Mutex<Option<String>>,
+}
+
+impl Callsite {
+    #[must_use]
+    pub const fn new(file: &'static str, line: u32, condition: &'static str) -> Self {
+        Callsite {
+            file,
+            line,
+            condition,
+            registered: AtomicBool::new(false),
+            count: AtomicU64::new(0),
+            first_seen: AtomicU64::new(0),
+            last_seen: AtomicU64::new(0),
+            last_reported: AtomicU64::new(0),
+            message: Mutex::new(None),
+        }
+    }
+
+    /// Handles a violation according to the current [`Mode`], returning `true`
+    /// if it should be reported.
+    ///
+    /// `test` should be `cfg!(test)` as evaluated at the assertion's callsite.
+    ///
+    /// # Panics
+    ///
+    /// If the resolved mode is [`Mode::Panic`] and the violation is not being
+    /// captured.
+    #[track_caller]
+    pub fn violated<F>(&'static self, policy: ReportPolicy, test: bool, message: F) -> bool
+    where
+        F: FnOnce() -> Option<String>,
+    {
+        let report = self.record(policy);
+
+        // Captured violations are neither reported nor escalated.
+        if CAPTURED.with_borrow(Option::is_some) {
+            let violation = CapturedViolation {
+                file: self.file,
+                line: self.line,
+                condition: self.condition,
+                message: message(),
+            };
+            CAPTURED.with_borrow_mut(|captured| captured.as_mut().unwrap().push(violation));
+
+            return false;
+        }
+
+        match Mode::resolve(test) {
+            Mode::Soft => report,
+            Mode::Panic => match message() {
+                Some(msg) => panic!(
+                    "Soft assert violated; condition={}; msg={msg}; file={}; line={}",
+                    self.condition, self.file, self.line
+                ),
+                None => panic!(
+                    "Soft assert violated; condition={}; file={}; line={}",
+                    self.condition, self.file, self.line
+                ),
+            },
+        }
+    }
+
+    /// Records a violation, returning `true` if `policy` says it should be
+    /// reported.
+    fn record(&'static self, policy: ReportPolicy) -> bool {
+        let now = now_nanos();
+
+        let _ = self
+            .first_seen
+            .compare_exchange(0, now, Ordering::Relaxed, Ordering::Relaxed);
+        self.last_seen.fetch_max(now, Ordering::Relaxed);
+        if !self.registered.swap(true, Ordering::Relaxed) {
+            REGISTRY
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(self);
+        }
+
+        let previous = self.count.fetch_add(1, Ordering::Relaxed);
+
+        policy.should_report(previous, monotonic_nanos(), &self.last_reported)
+    }
+
+    fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.first_seen.store(0, Ordering::Relaxed);
+        self.last_seen.store(0, Ordering::Relaxed);
+        self.last_reported.store(0, Ordering::Relaxed);
+        *self.message.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        self.registered.store(false, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> ViolationRecord {
+        let first_seen = self.first_seen.load(Ordering::Relaxed);
+        let last_seen = self.last_seen.load(Ordering::Relaxed);
+
+        ViolationRecord {
+            file: self.file,
+            line: self.line,
+            condition: self.condition,
+            message: self
+                .message
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+            count: self.count.load(Ordering::Relaxed),
+            first_seen: SystemTime::UNIX_EPOCH + Duration::from_nanos(first_seen),
+            last_seen: SystemTime::UNIX_EPOCH + Duration::from_nanos(last_seen),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub fn __report(callsite: &Callsite, message: Option<String>) {
+    let violation =
+        Violation { file: callsite.file, line: callsite.line, message: message.as_deref() };
+
+    // Clone the handler so the lock is not held while a (possibly re-entrant)
+    // custom handler runs.
+    let handler = HANDLER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    handler.handle(&violation);
+
+    *callsite
+        .message
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = message;
+}
+
 #[macro_export]
 macro_rules! soft_assert {
-    ($condition:expr $(,)*) => {{
-        use std::sync::atomic::{AtomicBool, Ordering};
+    ($condition:expr $(,)*) => {
+        $crate::__soft_assert!($crate::soft_assert::ReportPolicy::Once, $condition)
+    };
+
+    ($condition:expr, $($fmt:tt)*) => {
+        $crate::__soft_assert!($crate::soft_assert::ReportPolicy::Once, $condition, $($fmt)*)
+    };
+}
 
-        static TRIPPED: AtomicBool = AtomicBool::new(false);
-        if !$condition && !TRIPPED.swap(true, Ordering::Relaxed) {
-            eprintln!("Soft assert violated; file={}; line={}", file!(), line!());
+/// Like [`soft_assert!`] but re-arms once `interval` has elapsed since the
+/// last report.
+///
+/// ```rust
+/// # toolbox::soft_assert::set_mode(toolbox::soft_assert::Mode::Soft);
+/// use std::time::Duration;
+///
+/// use toolbox::soft_assert_every;
+///
+/// let queue_len = 10;
+/// soft_assert_every!(Duration::from_secs(60), queue_len < 5, "queue_len={queue_len}");
+/// ```
+#[macro_export]
+macro_rules! soft_assert_every {
+    ($interval:expr, $condition:expr $(,)*) => {
+        $crate::__soft_assert!($crate::soft_assert::ReportPolicy::Every($interval), $condition)
+    };
+
+    ($interval:expr, $condition:expr, $($fmt:tt)*) => {
+        $crate::__soft_assert!(
+            $crate::soft_assert::ReportPolicy::Every($interval),
+            $condition,
+            $($fmt)*
+        )
+    };
+}
+
+/// Like [`soft_assert!`] but reports every `n`th violation.
+///
+/// ```rust
+/// # toolbox::soft_assert::set_mode(toolbox::soft_assert::Mode::Soft);
+/// use toolbox::soft_assert_every_n;
+///
+/// for i in 0..100 {
+///     // Reports i=0, i=10, ..., i=90.
+///     soft_assert_every_n!(10, i > 100, "i={i}");
+/// }
+/// ```
+#[macro_export]
+macro_rules! soft_assert_every_n {
+    ($n:expr, $condition:expr $(,)*) => {
+        $crate::__soft_assert!($crate::soft_assert::ReportPolicy::EveryN($n), $condition)
+    };
+
+    ($n:expr, $condition:expr, $($fmt:tt)*) => {
+        $crate::__soft_assert!($crate::soft_assert::ReportPolicy::EveryN($n), $condition, $($fmt)*)
+    };
+}
+
+/// Like [`soft_assert!`] but compares two expressions for equality, reporting
+/// both values (using [`Debug`]) on violation.
+///
+/// ```rust
+/// # toolbox::soft_assert::set_mode(toolbox::soft_assert::Mode::Soft);
+/// use toolbox::soft_assert_eq;
+///
+/// let expected = 3;
+/// soft_assert_eq!(1 + 1, expected, "bad math");
+/// ```
+#[macro_export]
+macro_rules! soft_assert_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__soft_assert_cmp!($left, ==, $right, "")
+    };
+
+    ($left:expr, $right:expr, $($fmt:tt)+) => {
+        $crate::__soft_assert_cmp!($left, ==, $right, "{}; ", format_args!($($fmt)+))
+    };
+}
+
+/// Like [`soft_assert!`] but asserts two expressions are not equal, reporting
+/// both values (using [`Debug`]) on violation.
+#[macro_export]
+macro_rules! soft_assert_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__soft_assert_cmp!($left, !=, $right, "")
+    };
+
+    ($left:expr, $right:expr, $($fmt:tt)+) => {
+        $crate::__soft_assert_cmp!($left, !=, $right, "{}; ", format_args!($($fmt)+))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __soft_assert_cmp {
+    ($left:expr, $op:tt, $right:expr, $prefix:literal $(, $arg:expr)?) => {
+        match (&$left, &$right) {
+            (left, right) => $crate::__soft_assert!(
+                @ $crate::soft_assert::ReportPolicy::Once,
+                stringify!($left $op $right),
+                *left $op *right,
+                concat!($prefix, "left={:?}; right={:?}"),
+                $($arg,)?
+                left,
+                right,
+            ),
         }
-    }};
+    };
+}
+
+/// Like [`soft_assert!`] but asserts an expression matches a pattern, reporting
+/// the value (using [`Debug`]) on violation.
+///
+/// ```rust
+/// # toolbox::soft_assert::set_mode(toolbox::soft_assert::Mode::Soft);
+/// use toolbox::soft_assert_matches;
+///
+/// let res: Result<u32, &str> = Err("timeout");
+/// soft_assert_matches!(res, Ok(n) if n > 0);
+/// ```
+#[macro_export]
+macro_rules! soft_assert_matches {
+    ($value:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {
+        match $value {
+            $pattern $(if $guard)? => {}
+            ref value => $crate::__soft_assert!(
+                @ $crate::soft_assert::ReportPolicy::Once,
+                concat!("matches!(", stringify!($value), ", ", stringify!($pattern $(if $guard)?), ")"),
+                false,
+                "value={:?}",
+                value,
+            ),
+        }
+    };
 
-    ($condition:expr, $($fmt:tt)*) => {{
-        use std::sync::atomic::{AtomicBool, Ordering};
+    ($value:expr, $pattern:pat $(if $guard:expr)?, $($fmt:tt)+) => {
+        match $value {
+            $pattern $(if $guard)? => {}
+            ref value => $crate::__soft_assert!(
+                @ $crate::soft_assert::ReportPolicy::Once,
+                concat!("matches!(", stringify!($value), ", ", stringify!($pattern $(if $guard)?), ")"),
+                false,
+                "{}; value={:?}",
+                format_args!($($fmt)+),
+                value,
+            ),
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __soft_assert {
+    (@ $policy:expr, $text:expr, $condition:expr $(,)*) => {{
+        static CALLSITE: $crate::soft_assert::Callsite =
+            $crate::soft_assert::Callsite::new(file!(), line!(), $text);
+        if !$condition && CALLSITE.violated($policy, cfg!(test), || None) {
+            $crate::soft_assert::__report(&CALLSITE, None);
+        }
+    }};
 
-        static TRIPPED: AtomicBool = AtomicBool::new(false);
-        if !$condition && !TRIPPED.swap(true, Ordering::Relaxed) {
-            eprintln!("Soft assert violated; msg={}; file={}; line={}", format!($($fmt)*), file!(), line!());
+    (@ $policy:expr, $text:expr, $condition:expr, $($fmt:tt)*) => {{
+        static CALLSITE: $crate::soft_assert::Callsite =
+            $crate::soft_assert::Callsite::new(file!(), line!(), $text);
+        if !$condition && CALLSITE.violated($policy, cfg!(test), || Some(format!($($fmt)*))) {
+            $crate::soft_assert::__report(&CALLSITE, Some(format!($($fmt)*)));
         }
     }};
+
+    ($policy:expr, $condition:expr $(,)*) => {
+        $crate::__soft_assert!(@ $policy, stringify!($condition), $condition)
+    };
+
+    ($policy:expr, $condition:expr, $($fmt:tt)*) => {
+        $crate::__soft_assert!(@ $policy, stringify!($condition), $condition, $($fmt)*)
+    };
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
     #[test]
     fn soft_assert() {
-        soft_assert!(true, "msg0");
-        soft_assert!(false, "msg1");
-        soft_assert!(true);
-        soft_assert!(false);
-        soft_assert!(true, "msg0",);
-        soft_assert!(false, "msg1",);
-        soft_assert!(true,);
-        soft_assert!(false,);
-
-        let val = 10;
-        soft_assert!(false, "msg; val={val}");
-        soft_assert!(false, "msg; val={}", val);
-        soft_assert!(false, "msg; val={val}",);
-        soft_assert!(false, "msg; val={}", val,);
-        soft_assert!(true, "msg; val={val}");
-        soft_assert!(true, "msg; val={}", val);
-        soft_assert!(true, "msg; val={val}",);
-        soft_assert!(true, "msg; val={}", val,);
+        let violations = capture(|| {
+            soft_assert!(true, "msg0");
+            soft_assert!(false, "msg1");
+            soft_assert!(true);
+            soft_assert!(false);
+            soft_assert!(true, "msg0",);
+            soft_assert!(false, "msg1",);
+            soft_assert!(true,);
+            soft_assert!(false,);
+
+            let val = 10;
+            soft_assert!(false, "msg; val={val}");
+            soft_assert!(false, "msg; val={}", val);
+            soft_assert!(false, "msg; val={val}",);
+            soft_assert!(false, "msg; val={}", val,);
+            soft_assert!(true, "msg; val={val}");
+            soft_assert!(true, "msg; val={}", val);
+            soft_assert!(true, "msg; val={val}",);
+            soft_assert!(true, "msg; val={}", val,);
+        });
+
+        assert_eq!(violations.len(), 8);
+        assert_eq!(violations[0].message.as_deref(), Some("msg1"));
+        assert_eq!(violations[1].message, None);
+        assert_eq!(violations[7].message.as_deref(), Some("msg; val=10"));
+    }
+
+    #[test]
+    fn custom_handler() {
+        static SEEN: Mutex<Vec<(u32, Option<String>)>> = Mutex::new(Vec::new());
+
+        set_violation_handler(ViolationHandler::custom(|violation| {
+            if violation.file == file!() {
+                SEEN.lock()
+                    .unwrap()
+                    .push((violation.line, violation.message.map(ToString::to_string)));
+            }
+        }));
+        let first = line!() + 4;
+        let second = line!() + 5;
+        with_mode(Mode::Soft, || {
+            for i in 0..3 {
+                soft_assert!(i > 5, "i={i}");
+            }
+            soft_assert!(false);
+        });
+        set_violation_handler(ViolationHandler::Stderr);
+
+        // Other tests in this file may report concurrently.
+        let seen = SEEN.lock().unwrap();
+        let seen: Vec<_> = seen
+            .iter()
+            .filter(|(line, _)| (first..=second).contains(line))
+            .collect();
+        assert_eq!(seen, [&(first, Some("i=0".to_string())), &(second, None)]);
+    }
+
+    #[test]
+    fn violations_are_counted() {
+        let line = line!() + 3;
+        with_mode(Mode::Soft, || {
+            for i in 0..10 {
+                soft_assert!(i % 3 != 0, "i={i}");
+            }
+        });
+
+        let record = violations()
+            .into_iter()
+            .find(|record| record.file == file!() && record.line == line)
+            .unwrap();
+        assert_eq!(record.condition, "i % 3 != 0");
+        assert_eq!(record.message.as_deref(), Some("i=0"));
+        assert_eq!(record.count, 4);
+        assert!(record.first_seen <= record.last_seen);
+    }
+
+    #[test]
+    fn every_n_policy() {
+        static CALLSITE: Callsite = Callsite::new(file!(), line!(), "every_n");
+
+        let reported: Vec<_> = (0..25)
+            .filter(|_| CALLSITE.record(ReportPolicy::EveryN(10)))
+            .collect();
+        assert_eq!(reported, [0, 10, 20]);
+    }
+
+    #[test]
+    fn every_policy() {
+        static CALLSITE: Callsite = Callsite::new(file!(), line!(), "every");
+        let policy = ReportPolicy::Every(Duration::from_millis(50));
+
+        assert!(CALLSITE.record(policy));
+        assert!(!CALLSITE.record(policy));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(CALLSITE.record(policy));
+        assert!(!CALLSITE.record(policy));
+    }
+
+    #[test]
+    fn monotonic_nanos_never_marks_unreported() {
+        let first = monotonic_nanos();
+
+        assert!(first > 0);
+        assert!(monotonic_nanos() >= first);
+    }
+
+    #[test]
+    fn reset_rearms() {
+        static CALLSITE: Callsite = Callsite::new(file!(), line!(), "reset");
+
+        assert!(CALLSITE.record(ReportPolicy::Once));
+        assert!(!CALLSITE.record(ReportPolicy::Once));
+        CALLSITE.reset();
+        assert_eq!(CALLSITE.count.load(Ordering::Relaxed), 0);
+        assert!(CALLSITE.record(ReportPolicy::Once));
+    }
+
+    #[test]
+    fn rate_limited_macros() {
+        let violations = capture(|| {
+            for i in 0..3 {
+                soft_assert_every!(Duration::from_secs(60), i > 5);
+                soft_assert_every!(Duration::from_secs(60), i > 5, "i={i}");
+                soft_assert_every_n!(2, i > 5);
+                soft_assert_every_n!(2, i > 5, "i={}", i,);
+            }
+        });
+
+        // Captured violations bypass the report policy.
+        assert_eq!(violations.len(), 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "Soft assert violated; condition=one > 2; msg=one=1")]
+    fn panics_in_tests() {
+        let one = 1;
+        soft_assert!(one > 2, "one={one}");
+    }
+
+    #[test]
+    fn thread_mode_overrides_test_default() {
+        with_mode(Mode::Soft, || soft_assert!(false));
+
+        let result = std::panic::catch_unwind(|| with_mode(Mode::Panic, || soft_assert!(false)));
+        assert!(result.is_err());
+        assert_eq!(THREAD_MODE.get(), None);
+    }
+
+    #[test]
+    fn capture_is_scoped() {
+        let outer = capture(|| {
+            soft_assert!(false, "outer");
+            let inner = capture(|| soft_assert!(false, "inner"));
+            assert_eq!(inner.len(), 1);
+            assert_eq!(inner[0].message.as_deref(), Some("inner"));
+        });
+
+        assert_eq!(outer.len(), 1);
+        assert_eq!(outer[0].message.as_deref(), Some("outer"));
+        assert!(CAPTURED.with_borrow(Option::is_none));
+    }
+
+    #[test]
+    fn comparison_macros() {
+        let violations = capture(|| {
+            soft_assert_eq!(1 + 1, 2);
+            soft_assert_eq!(1 + 1, 3);
+            soft_assert_eq!("a", "b", "strings; n={}", 1);
+            soft_assert_ne!(1, 2);
+            soft_assert_ne!(vec![1], vec![1],);
+            soft_assert_matches!(Some(5), Some(n) if n > 3);
+            soft_assert_matches!(Some(2), Some(n) if n > 3);
+            soft_assert_matches!(Err::<u8, _>("timeout"), Ok(_), "res");
+        });
+
+        let violations: Vec<_> = violations
+            .iter()
+            .map(|violation| (violation.condition, violation.message.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                ("1 + 1 == 3", "left=2; right=3"),
+                ("\"a\" == \"b\"", "strings; n=1; left=\"a\"; right=\"b\""),
+                ("vec![1] != vec![1]", "left=[1]; right=[1]"),
+                ("matches!(Some(2), Some(n) if n > 3)", "value=Some(2)"),
+                ("matches!(Err::<u8, _>(\"timeout\"), Ok(_))", "res; value=Err(\"timeout\")"),
+            ]
+        );
     }
 }
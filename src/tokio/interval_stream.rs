@@ -3,6 +3,11 @@ use std::pin::{pin, Pin};
 use std::task::Poll;
 
 use futures::{ready, Stream};
+#[cfg(feature = "shutdown")]
+use tokio_util::sync::WaitForCancellationFutureOwned;
+
+#[cfg(feature = "shutdown")]
+use crate::shutdown::Shutdown;
 
 /// Creates and polls a future on an interval, producing a stream.
 ///
@@ -32,6 +37,8 @@ where
     poll: Pin<Box<dyn Fn() -> Fut + Send>>,
 
     in_progress: Option<Fut>,
+    #[cfg(feature = "shutdown")]
+    cancelled: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
 }
 
 impl<Fut, Output> IntervalStream<Fut>
@@ -39,7 +46,47 @@ where
     Fut: Future<Output = Output> + Unpin,
 {
     pub fn new(interval: tokio::time::Interval, poll: Pin<Box<dyn Fn() -> Fut + Send>>) -> Self {
-        IntervalStream { interval, poll, in_progress: None }
+        IntervalStream {
+            interval,
+            poll,
+            in_progress: None,
+            #[cfg(feature = "shutdown")]
+            cancelled: None,
+        }
+    }
+
+    /// Ends the stream once `shutdown` fires.
+    ///
+    /// Unlike [`ShutdownStreamExt`](crate::shutdown::ShutdownStreamExt), a
+    /// future that is already in progress when `shutdown` fires is driven to
+    /// completion and its output yielded before the stream ends.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// use futures::FutureExt;
+    /// use futures::StreamExt;
+    /// use toolbox::shutdown::Shutdown;
+    /// use toolbox::tokio::IntervalStream;
+    ///
+    /// let shutdown = Shutdown::new();
+    /// let interval = tokio::time::interval(std::time::Duration::from_millis(1));
+    /// let mut stream =
+    ///     IntervalStream::new(interval, Box::pin(|| futures::future::ready(()).boxed()))
+    ///         .with_shutdown(&shutdown);
+    ///
+    /// assert_eq!(stream.next().await, Some(()));
+    /// shutdown.shutdown();
+    /// assert_eq!(stream.next().await, None);
+    /// # })
+    /// ```
+    #[cfg(feature = "shutdown")]
+    #[must_use]
+    pub fn with_shutdown(mut self, shutdown: &Shutdown) -> Self {
+        self.cancelled = Some(Box::pin(shutdown.token.clone().cancelled_owned()));
+
+        self
     }
 }
 
@@ -63,6 +110,14 @@ where
             return Poll::Ready(Some(output));
         }
 
+        // Stop creating new futures once shutdown has fired.
+        #[cfg(feature = "shutdown")]
+        if let Some(cancelled) = &mut this.cancelled {
+            if cancelled.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+        }
+
         // Poll the interval to see if we should create a new future.
         ready!(this.interval.poll_tick(cx));
 
@@ -0,0 +1,635 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use thiserror::Error;
+use tracing::Subscriber;
+use tracing_appender::non_blocking::NonBlockingBuilder;
+use tracing_appender::rolling::Rotation;
+use tracing_subscriber::filter::ParseError;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::{SubscriberInitExt, TryInitError};
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use super::{
+    read_filter_file, spawn_dropped_lines_reporter, ReloadHandle, ReloadableFilter, RollingFile,
+    RollingOptions, TracingHandle,
+};
+
+pub(super) type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Configures & installs the global tracing subscriber.
+///
+/// Logs are written to stderr and, if a log directory is configured, to a
+/// rolling log file. Journald, syslog & OTLP outputs can be added as well. Both
+/// outputs are filtered independently using directives read from environment
+/// variables (falling back to per-output defaults). Log files are rotated &
+/// pruned by a [`RollingFile`]. Filters can be swapped at runtime through the
+/// returned [`TracingHandle`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use tracing_appender::rolling::Rotation;
+/// use toolbox::tracing::{LogFormat, TracingBuilder};
+///
+/// let _handle = TracingBuilder::new("my-crate")
+///     .log_directory("/var/log/my-crate")
+///     .rotation(Rotation::DAILY)
+///     .max_files(7)
+///     .max_file_size(64 * 1024 * 1024)
+///     .max_total_size(1024 * 1024 * 1024)
+///     .stdout_format(LogFormat::Compact)
+///     .filter_file("/etc/my-crate/log-filters")
+///     .watch_filter_file(Duration::from_secs(5))
+///     .init()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct TracingBuilder {
+    pkg_name: String,
+    log_directory: Option<PathBuf>,
+    rolling: RollingOptions,
+    buffered_lines_limit: Option<usize>,
+    lossy: bool,
+    dropped_lines_interval: Duration,
+    stdout_format: LogFormat,
+    file_format: LogFormat,
+    stdout_env_var: String,
+    file_env_var: String,
+    stdout_default_filter: String,
+    file_default_filter: Option<String>,
+    filter_file: Option<PathBuf>,
+    watch_interval: Option<Duration>,
+    #[cfg(all(unix, feature = "tracing_sighup"))]
+    reload_on_sighup: bool,
+    #[cfg(feature = "tracing_otlp")]
+    otlp: Option<super::OtlpConfig>,
+    #[cfg(feature = "tracing_error")]
+    span_traces: Option<String>,
+    #[cfg(unix)]
+    journald: Option<super::JournaldConfig>,
+    syslog: Option<super::SyslogConfig>,
+    span_timing: Option<super::SpanTimingConfig>,
+    sampling: Option<super::SamplingConfig>,
+    #[cfg(feature = "tracing_redact")]
+    redaction: Option<super::RedactionConfig>,
+    ansi: Option<bool>,
+    thread_names: bool,
+    span_events: FmtSpan,
+}
+
+impl TracingBuilder {
+    /// Creates a builder with the same defaults as
+    /// [`setup_tracing`](super::setup_tracing).
+    pub fn new(pkg_name: impl Into<String>) -> Self {
+        TracingBuilder {
+            pkg_name: pkg_name.into(),
+            log_directory: None,
+            rolling: RollingOptions::default(),
+            buffered_lines_limit: None,
+            lossy: true,
+            dropped_lines_interval: Duration::from_secs(60),
+            stdout_format: LogFormat::Full,
+            file_format: LogFormat::Json,
+            stdout_env_var: "RUST_LOG".to_string(),
+            file_env_var: "RUST_FILE_LOG".to_string(),
+            stdout_default_filter: "info".to_string(),
+            file_default_filter: None,
+            filter_file: None,
+            watch_interval: None,
+            #[cfg(all(unix, feature = "tracing_sighup"))]
+            reload_on_sighup: false,
+            #[cfg(feature = "tracing_otlp")]
+            otlp: None,
+            #[cfg(feature = "tracing_error")]
+            span_traces: None,
+            #[cfg(unix)]
+            journald: None,
+            syslog: None,
+            span_timing: None,
+            sampling: None,
+            #[cfg(feature = "tracing_redact")]
+            redaction: None,
+            ansi: None,
+            thread_names: false,
+            span_events: FmtSpan::NONE,
+        }
+    }
+
+    /// Enables the file output, writing logs to `{pkg_name}.log.*` files in
+    /// `log_directory`.
+    pub fn log_directory(mut self, log_directory: impl Into<PathBuf>) -> Self {
+        self.log_directory = Some(log_directory.into());
+
+        self
+    }
+
+    /// How often the log file is rotated, defaults to [`Rotation::HOURLY`].
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rolling.rotation = rotation;
+
+        self
+    }
+
+    /// The maximum number of log files to retain, by default all files are
+    /// retained.
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.rolling.max_files = Some(max_files);
+
+        self
+    }
+
+    /// The maximum size in bytes of a single log file before a new file is
+    /// started, by default files are only rotated by time.
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.rolling.max_file_size = Some(bytes);
+
+        self
+    }
+
+    /// The maximum combined size in bytes of all retained log files, by
+    /// default all files are retained.
+    pub fn max_total_size(mut self, bytes: u64) -> Self {
+        self.rolling.max_total_size = Some(bytes);
+
+        self
+    }
+
+    /// Whether to gzip log files once they are rotated out, defaults to
+    /// `false`.
+    #[cfg(feature = "tracing_gzip")]
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.rolling.compress = compress;
+
+        self
+    }
+
+    /// The number of lines buffered for the file writer, defaults to
+    /// `128_000`.
+    pub fn buffered_lines_limit(mut self, lines: usize) -> Self {
+        self.buffered_lines_limit = Some(lines);
+
+        self
+    }
+
+    /// Whether to drop lines (rather than block the logging thread) when the
+    /// file writer's buffer is full, defaults to `true`.
+    ///
+    /// Dropped lines are counted, see [`TracingHandle::dropped_lines`].
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+
+        self
+    }
+
+    /// How often dropped lines are reported as a `WARN` event, defaults to
+    /// 60 seconds.
+    pub fn dropped_lines_interval(mut self, interval: Duration) -> Self {
+        self.dropped_lines_interval = interval;
+
+        self
+    }
+
+    /// Format of the stderr output, defaults to [`LogFormat::Full`].
+    pub fn stdout_format(mut self, format: LogFormat) -> Self {
+        self.stdout_format = format;
+
+        self
+    }
+
+    /// Format of the file output, defaults to [`LogFormat::Json`].
+    pub fn file_format(mut self, format: LogFormat) -> Self {
+        self.file_format = format;
+
+        self
+    }
+
+    /// Environment variable holding the stderr filter, defaults to `RUST_LOG`.
+    pub fn stdout_env_var(mut self, var: impl Into<String>) -> Self {
+        self.stdout_env_var = var.into();
+
+        self
+    }
+
+    /// Environment variable holding the file filter, defaults to
+    /// `RUST_FILE_LOG`.
+    pub fn file_env_var(mut self, var: impl Into<String>) -> Self {
+        self.file_env_var = var.into();
+
+        self
+    }
+
+    /// Filter used for stderr if the env var is unset, defaults to `info`.
+    pub fn stdout_default_filter(mut self, directives: impl Into<String>) -> Self {
+        self.stdout_default_filter = directives.into();
+
+        self
+    }
+
+    /// Filter used for the file output if the env var is unset, defaults to
+    /// `info,{pkg_name}=debug`.
+    pub fn file_default_filter(mut self, directives: impl Into<String>) -> Self {
+        self.file_default_filter = Some(directives.into());
+
+        self
+    }
+
+    /// File of `VAR=directives` lines (or, with the `tracing_config` feature, a
+    /// TOML or JSON config) consulted after the env vars when loading or
+    /// reloading filters, see [`ReloadHandle`] for the formats.
+    pub fn filter_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.filter_file = Some(path.into());
+
+        self
+    }
+
+    /// Polls the filter file every `poll_interval` & reloads the filters when
+    /// it changes.
+    pub fn watch_filter_file(mut self, poll_interval: Duration) -> Self {
+        self.watch_interval = Some(poll_interval);
+
+        self
+    }
+
+    /// Reloads the filters whenever the process receives `SIGHUP`.
+    #[cfg(all(unix, feature = "tracing_sighup"))]
+    pub fn reload_on_sighup(mut self, reload_on_sighup: bool) -> Self {
+        self.reload_on_sighup = reload_on_sighup;
+
+        self
+    }
+
+    /// Exports spans to an OpenTelemetry collector.
+    #[cfg(feature = "tracing_otlp")]
+    pub fn otlp(mut self, config: super::OtlpConfig) -> Self {
+        self.otlp = Some(config);
+
+        self
+    }
+
+    /// Sends logs to journald.
+    #[cfg(unix)]
+    pub fn journald(mut self, config: super::JournaldConfig) -> Self {
+        self.journald = Some(config);
+
+        self
+    }
+
+    /// Sends logs to a syslog daemon.
+    pub fn syslog(mut self, config: super::SyslogConfig) -> Self {
+        self.syslog = Some(config);
+
+        self
+    }
+
+    /// Warns about slow spans & periodically logs span timing summaries.
+    pub fn span_timing(mut self, config: super::SpanTimingConfig) -> Self {
+        self.span_timing = Some(config);
+
+        self
+    }
+
+    /// Samples events from noisy targets before they reach any output.
+    pub fn sampling(mut self, config: super::SamplingConfig) -> Self {
+        self.sampling = Some(config);
+
+        self
+    }
+
+    /// Records the spans `directives` enable, so errors & panics can report the
+    /// spans they occurred in, defaults to none.
+    ///
+    /// Only spans are affected, events are left to the outputs' filters.
+    #[cfg(feature = "tracing_error")]
+    pub fn span_traces(mut self, directives: impl Into<String>) -> Self {
+        self.span_traces = Some(directives.into());
+
+        self
+    }
+
+    /// Masks secrets in fields before they are written to stderr, file,
+    /// journald or syslog.
+    ///
+    /// Spans exported via OTLP are not redacted.
+    #[cfg(feature = "tracing_redact")]
+    pub fn redaction(mut self, config: super::RedactionConfig) -> Self {
+        self.redaction = Some(config);
+
+        self
+    }
+
+    /// Whether to use ANSI colors on stderr.
+    ///
+    /// Defaults to enabled unless the `NO_COLOR` env var is set. The file
+    /// output never uses ANSI colors.
+    pub fn ansi(mut self, ansi: bool) -> Self {
+        self.ansi = Some(ansi);
+
+        self
+    }
+
+    /// Whether to include thread names in both outputs, defaults to `false`.
+    pub fn thread_names(mut self, thread_names: bool) -> Self {
+        self.thread_names = thread_names;
+
+        self
+    }
+
+    /// Which span lifecycle events to log, defaults to [`FmtSpan::NONE`].
+    pub fn span_events(mut self, span_events: FmtSpan) -> Self {
+        self.span_events = span_events;
+
+        self
+    }
+
+    /// Installs the configured subscriber as the global default.
+    ///
+    /// The returned handle must be held for as long as logs should be written
+    /// to file.
+    pub fn init(self) -> Result<TracingHandle, TracingError> {
+        let (layers, mut handle) = self.layers()?;
+
+        // Start reporting dropped lines.
+        if self.lossy && !handle.counters.is_empty() {
+            handle.reporters.push(spawn_dropped_lines_reporter(
+                handle.counters.clone(),
+                self.dropped_lines_interval,
+            )?);
+        }
+
+        // Combine stdout & file layer.
+        tracing_subscriber::registry().with(layers).try_init()?;
+
+        // Start the reload triggers, now that there's a subscriber to reload.
+        if let Some(poll_interval) = self.watch_interval {
+            handle
+                .reporters
+                .extend(handle.reload.watch_filter_file(poll_interval)?);
+        }
+        #[cfg(all(unix, feature = "tracing_sighup"))]
+        if self.reload_on_sighup {
+            handle.sighup = Some(handle.reload.reload_on_sighup()?);
+        }
+
+        Ok(handle)
+    }
+
+    pub(super) fn layers(&self) -> Result<(Vec<BoxedLayer>, TracingHandle), TracingError> {
+        let mut layers: Vec<BoxedLayer> = Vec::default();
+        let mut counters = Vec::default();
+        let mut reporters = Vec::default();
+        #[cfg(feature = "tracing_redact")]
+        let redactor = self
+            .redaction
+            .as_ref()
+            .map(super::RedactionConfig::build)
+            .transpose()?;
+        // Wraps an output so it only receives redacted fields.
+        let redacted = |layer: BoxedLayer| -> BoxedLayer {
+            #[cfg(feature = "tracing_redact")]
+            if let Some(redactor) = &redactor {
+                return redactor.layer(layer).boxed();
+            }
+
+            layer
+        };
+        let overrides = match &self.filter_file {
+            Some(path) => read_filter_file(path)?,
+            None => HashMap::default(),
+        };
+
+        // Setup span trace layer (if requested).
+        #[cfg(feature = "tracing_error")]
+        if let Some(directives) = &self.span_traces {
+            let fields = super::SpanTraceFields {
+                #[cfg(feature = "tracing_redact")]
+                redactor: redactor.clone(),
+            };
+            layers.push(super::span_trace_layer(directives, fields)?);
+        }
+
+        // Setup sampling layer (if requested), this applies to all outputs.
+        if let Some(config) = &self.sampling {
+            layers.push(config.layer());
+        }
+
+        // Setup stdout layer.
+        let (stdout_filter, stdout_reload) = ReloadableFilter::new(
+            "stdout",
+            self.stdout_env_var.clone(),
+            self.stdout_default_filter.clone(),
+            &overrides,
+        )?;
+        let stdout_layer = redacted(self.fmt_layer(self.stdout_format, std::io::stderr, self.ansi));
+        layers.push(stdout_layer.with_filter(stdout_filter).boxed());
+
+        // Setup file layer (if requested).
+        let (file_guard, file_reload) = match &self.log_directory {
+            Some(directory) => {
+                let file = RollingFile::new(
+                    directory,
+                    format!("{}.log", self.pkg_name),
+                    self.rolling.clone(),
+                )?;
+                let mut non_blocking = NonBlockingBuilder::default()
+                    .lossy(self.lossy)
+                    .thread_name("tracing-file");
+                if let Some(lines) = self.buffered_lines_limit {
+                    non_blocking = non_blocking.buffered_lines_limit(lines);
+                }
+                let (file_writer, file_guard) = non_blocking.finish(file);
+                counters.push(file_writer.error_counter());
+
+                // Load the user's file filter else fallback to the default.
+                let default_filter = self
+                    .file_default_filter
+                    .clone()
+                    .unwrap_or_else(|| format!("info,{}=debug", self.pkg_name));
+                let (file_filter, file_reload) = ReloadableFilter::new(
+                    "file",
+                    self.file_env_var.clone(),
+                    default_filter,
+                    &overrides,
+                )?;
+
+                let file_layer =
+                    redacted(self.fmt_layer(self.file_format, file_writer, Some(false)));
+                layers.push(file_layer.with_filter(file_filter).boxed());
+
+                (Some(file_guard), Some(file_reload))
+            }
+            None => (None, None),
+        };
+
+        // Setup journald & syslog layers (if requested).
+        #[cfg(unix)]
+        if let Some(config) = &self.journald {
+            layers.push(redacted(config.layer()?));
+        }
+        if let Some(config) = &self.syslog {
+            layers.push(redacted(config.layer()?));
+        }
+
+        // Setup span timing layer (if requested).
+        if let Some(config) = &self.span_timing {
+            let (timing_layer, timing_reporter) = config.layer()?;
+            layers.push(timing_layer);
+            reporters.extend(timing_reporter);
+        }
+
+        // Setup OTLP layer (if requested).
+        #[cfg(feature = "tracing_otlp")]
+        let otlp = match &self.otlp {
+            Some(config) => {
+                let (otlp_layer, otlp_guard) = config.layer()?;
+                layers.push(otlp_layer);
+
+                Some(otlp_guard)
+            }
+            None => None,
+        };
+
+        let reload = ReloadHandle {
+            filter_file: self.filter_file.clone(),
+            stdout: stdout_reload,
+            file: file_reload,
+        };
+
+        Ok((
+            layers,
+            TracingHandle {
+                reporters,
+                guards: file_guard.into_iter().collect(),
+                counters,
+                reload,
+                #[cfg(all(unix, feature = "tracing_sighup"))]
+                sighup: None,
+                #[cfg(feature = "tracing_otlp")]
+                otlp,
+            },
+        ))
+    }
+
+    fn fmt_layer<S, W>(
+        &self,
+        format: LogFormat,
+        writer: W,
+        ansi: Option<bool>,
+    ) -> Box<dyn Layer<S> + Send + Sync>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+        W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+    {
+        let mut layer = tracing_subscriber::fmt::layer()
+            .with_writer(writer)
+            .with_thread_names(self.thread_names)
+            .with_span_events(self.span_events.clone());
+        if let Some(ansi) = ansi {
+            layer = layer.with_ansi(ansi);
+        }
+
+        match format {
+            LogFormat::Full => layer.boxed(),
+            LogFormat::Compact => layer.compact().boxed(),
+            LogFormat::Pretty => layer.pretty().boxed(),
+            LogFormat::Json => layer.json().boxed(),
+        }
+    }
+}
+
+/// Output format of a log layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable, single line per event.
+    Full,
+    /// Like [`LogFormat::Full`] but more terse.
+    Compact,
+    /// Human readable, multiple lines per event.
+    Pretty,
+    /// Newline delimited JSON.
+    Json,
+}
+
+#[derive(Debug, Error)]
+pub enum TracingError {
+    #[error("Invalid filter; var={var}; directives={directives}; err={err}")]
+    Filter { var: String, directives: String, err: ParseError },
+    #[error("Failed to create log file; err={0}")]
+    Appender(#[from] std::io::Error),
+    #[error("Failed to connect log output; err={0}")]
+    Output(std::io::Error),
+    #[error("Failed to read filter file; path={}; err={err}", path.display())]
+    FilterFile { path: PathBuf, err: std::io::Error },
+    #[error("Invalid filter config; path={}; err={err}", path.display())]
+    FilterConfig { path: PathBuf, err: String },
+    #[cfg(feature = "tracing_redact")]
+    #[error("Invalid redaction pattern; pattern={pattern}; err={err}")]
+    Redaction { pattern: String, err: regex::Error },
+    #[error("Failed to spawn background thread; err={0}")]
+    Thread(std::io::Error),
+    #[error("Failed to register signal handler; err={0}")]
+    Signal(std::io::Error),
+    #[error("Failed to reload filter; err={0}")]
+    Reload(#[from] tracing_subscriber::reload::Error),
+    #[cfg(feature = "tracing_otlp")]
+    #[error("Failed to build OTLP exporter; err={0}")]
+    Otlp(#[from] opentelemetry_otlp::ExporterBuildError),
+    #[error("Failed to set global subscriber; err={0}")]
+    Init(#[from] TryInitError),
+}
+
+/// Parses `directives`, attributing any error to `name`.
+pub(super) fn parse_filter(name: &str, directives: &str) -> Result<EnvFilter, TracingError> {
+    EnvFilter::builder()
+        .parse(directives)
+        .map_err(|err| TracingError::Filter {
+            var: name.to_string(),
+            directives: directives.to_string(),
+            err,
+        })
+}
+
+/// Loads the filter from `var`, falling back to `default` if `var` is unset or
+/// empty.
+pub(super) fn load_filter(var: &str, default: &str) -> Result<EnvFilter, TracingError> {
+    let directives = std::env::var(var)
+        .ok()
+        .filter(|directives| !directives.is_empty())
+        .unwrap_or_else(|| default.to_string());
+
+    parse_filter(var, &directives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_filter_errors() {
+        let err = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .stdout_default_filter("info,[{")
+            .init()
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            TracingError::Filter { ref var, ref directives, .. }
+                if var == "TOOLBOX_TEST_UNSET_VAR" && directives == "info,[{"
+        ));
+    }
+
+    #[test]
+    fn load_filter_falls_back_to_default() {
+        let filter = load_filter("TOOLBOX_TEST_UNSET_VAR", "warn,toolbox=trace").unwrap();
+
+        assert_eq!(filter.to_string(), "toolbox=trace,warn");
+    }
+}
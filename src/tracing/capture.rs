@@ -0,0 +1,182 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// An event recorded by a [`CaptureLayer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedEvent {
+    pub level: Level,
+    pub target: String,
+    /// The event's message, if any.
+    pub message: Option<String>,
+    /// All other fields, `Debug` formatted (strings are recorded verbatim).
+    pub fields: BTreeMap<String, String>,
+    /// Names of the spans the event occurred in, outermost first.
+    pub spans: Vec<String>,
+}
+
+impl CapturedEvent {
+    /// The recorded value of field `name`.
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+/// A layer that records every event it sees.
+///
+/// Clones share the same recording, keep a clone to inspect the events after
+/// handing the layer to a subscriber.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureLayer {
+    events: Arc<Mutex<Vec<CapturedEvent>>>,
+}
+
+impl CaptureLayer {
+    /// All events recorded so far.
+    #[must_use]
+    pub fn events(&self) -> Vec<CapturedEvent> {
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl<S> Layer<S> for CaptureLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| span.name().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(CapturedEvent {
+                level: *event.metadata().level(),
+                target: event.metadata().target().to_string(),
+                message: visitor.message,
+                fields: visitor.fields,
+                spans,
+            });
+    }
+}
+
+/// Records an event's message & fields as strings.
+#[derive(Default)]
+pub(super) struct FieldVisitor {
+    pub(super) message: Option<String>,
+    pub(super) fields: BTreeMap<String, String>,
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: String) {
+        match field.name() {
+            "message" => self.message = Some(value),
+            name => {
+                self.fields.insert(name.to_string(), value);
+            }
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{value:?}"));
+    }
+}
+
+/// Runs `f` with a scoped subscriber that records all events emitted on the
+/// current thread.
+///
+/// # Example
+///
+/// ```rust
+/// use toolbox::tracing::capture_events;
+/// use tracing::Level;
+///
+/// let events = capture_events(|| tracing::warn!(attempt = 3, "Retrying"));
+///
+/// assert_eq!(events.len(), 1);
+/// assert_eq!(events[0].level, Level::WARN);
+/// assert_eq!(events[0].message.as_deref(), Some("Retrying"));
+/// assert_eq!(events[0].field("attempt"), Some("3"));
+/// ```
+pub fn capture_events(f: impl FnOnce()) -> Vec<CapturedEvent> {
+    let layer = CaptureLayer::default();
+    let subscriber = tracing_subscriber::registry().with(layer.clone());
+    tracing::subscriber::with_default(subscriber, f);
+
+    layer.events()
+}
+
+/// Renders the event emitted by `f` as `message; key=value; ...`.
+///
+/// Used by the soft assertion macros to describe violations.
+#[doc(hidden)]
+pub fn render_event(f: impl FnOnce()) -> Option<String> {
+    let event = capture_events(f).pop()?;
+    let parts: Vec<_> = event
+        .message
+        .into_iter()
+        .chain(
+            event
+                .fields
+                .iter()
+                .map(|(name, value)| format!("{name}={value}")),
+        )
+        .collect();
+
+    (!parts.is_empty()).then(|| parts.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_fields_and_spans() {
+        let events = capture_events(|| {
+            let _outer = tracing::info_span!("outer").entered();
+            let _inner = tracing::info_span!("inner").entered();
+
+            tracing::debug!(count = 2, name = "a", value = ?Some(1), "Hello {}", "world");
+        });
+
+        assert_eq!(
+            events,
+            [CapturedEvent {
+                level: Level::DEBUG,
+                target: module_path!().to_string(),
+                message: Some("Hello world".to_string()),
+                fields: BTreeMap::from([
+                    ("count".to_string(), "2".to_string()),
+                    ("name".to_string(), "a".to_string()),
+                    ("value".to_string(), "Some(1)".to_string()),
+                ]),
+                spans: vec!["outer".to_string(), "inner".to_string()],
+            }]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use tracing_appender::non_blocking::ErrorCounter;
+
+use super::{PeriodicReporter, TracingError};
+
+/// Spawns a reporter that periodically logs how many lines the non-blocking
+/// writers dropped.
+pub(super) fn spawn_dropped_lines_reporter(
+    counters: Vec<ErrorCounter>,
+    interval: Duration,
+) -> Result<PeriodicReporter, TracingError> {
+    let mut reported = 0;
+
+    PeriodicReporter::spawn("tracing-dropped-lines", interval, move || {
+        reported = report(&counters, reported);
+    })
+}
+
+/// Total lines dropped across `counters`.
+pub(super) fn dropped_lines(counters: &[ErrorCounter]) -> usize {
+    counters.iter().map(ErrorCounter::dropped_lines).sum()
+}
+
+/// Logs a warning if lines were dropped since `reported`, returning the new
+/// total.
+fn report(counters: &[ErrorCounter], reported: usize) -> usize {
+    let total = dropped_lines(counters);
+    if total > reported {
+        tracing::warn!(dropped_lines = total - reported, total, "Dropped log lines");
+    }
+
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use tracing_appender::non_blocking::NonBlockingBuilder;
+
+    use super::*;
+    use crate::tracing::capture_events;
+
+    struct SlowWriter;
+
+    impl io::Write for SlowWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            std::thread::sleep(Duration::from_millis(20));
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dropped_lines_are_reported() {
+        let (mut writer, _guard) = NonBlockingBuilder::default()
+            .buffered_lines_limit(1)
+            .lossy(true)
+            .finish(SlowWriter);
+        for _ in 0..10 {
+            io::Write::write_all(&mut writer, b"line\n").unwrap();
+        }
+        let counters = vec![writer.error_counter()];
+        let dropped = dropped_lines(&counters);
+        assert!(dropped > 0);
+
+        let events = capture_events(|| {
+            assert_eq!(report(&counters, 0), dropped);
+            assert_eq!(report(&counters, dropped), dropped);
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].field("dropped_lines"), Some(dropped.to_string().as_str()));
+        assert_eq!(events[0].field("total"), Some(dropped.to_string().as_str()));
+    }
+}
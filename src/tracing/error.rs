@@ -0,0 +1,341 @@
+use std::error::Error;
+use std::fmt;
+
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Metadata, Subscriber};
+use tracing_error::{ErrorLayer, ExtractSpanTrace};
+pub use tracing_error::{InstrumentError, InstrumentResult, SpanTrace, TracedError};
+use tracing_subscriber::field::{RecordFields, VisitOutput};
+use tracing_subscriber::fmt::format::{DefaultVisitor, Writer};
+use tracing_subscriber::fmt::FormatFields;
+use tracing_subscriber::layer::{Context, Filter};
+use tracing_subscriber::{EnvFilter, Layer};
+
+use super::{parse_filter, BoxedLayer, TracingError};
+
+/// An error's source chain & span context, flattened into strings for logging.
+///
+/// The span context is taken from the first [`TracedError`] in the chain (i.e.
+/// where the error was instrumented), falling back to the spans active when
+/// the report is created. Span traces are only captured for the spans enabled
+/// by [`TracingBuilder::span_traces`](super::TracingBuilder::span_traces).
+///
+/// # Example
+///
+/// ```rust
+/// use toolbox::tracing::{ErrorReport, InstrumentResult};
+///
+/// fn parse(input: &str) -> Result<u32, tracing_error::TracedError<std::num::ParseIntError>> {
+///     let _span = tracing::info_span!("parse", input).entered();
+///
+///     input.parse().in_current_span()
+/// }
+///
+/// let err = parse("abc").unwrap_err();
+/// let report = ErrorReport::new(&err);
+/// assert_eq!(report.error, "invalid digit found in string");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    /// The error's `Display` output.
+    pub error: String,
+    /// The `Display` output of each source, outermost first.
+    pub sources: Vec<String>,
+    /// The spans the error occurred in, formatted as
+    /// `root{fields}:leaf{fields}`.
+    pub spans: String,
+}
+
+impl ErrorReport {
+    #[must_use]
+    pub fn new(err: &(dyn Error + 'static)) -> Self {
+        let mut span_trace = None;
+        let mut sources = Vec::default();
+        let mut source = err.source();
+        while let Some(current) = source {
+            // Instrumented errors repeat their inner error, carrying its span trace.
+            match current.span_trace() {
+                Some(trace) => {
+                    span_trace.get_or_insert_with(|| format_span_trace(trace));
+                }
+                None => sources.push(current.to_string()),
+            }
+            source = current.source();
+        }
+
+        ErrorReport {
+            error: err.to_string(),
+            sources,
+            spans: span_trace.unwrap_or_else(|| format_span_trace(&SpanTrace::capture())),
+        }
+    }
+}
+
+/// Creates the layer recording the spans `directives` enable for span traces.
+pub(super) fn span_trace_layer(
+    directives: &str,
+    fields: SpanTraceFields,
+) -> Result<BoxedLayer, TracingError> {
+    let filter = parse_filter("span_traces", directives)?;
+
+    Ok(ErrorLayer::new(fields)
+        .with_filter(SpanFilter(filter))
+        .boxed())
+}
+
+/// Formats the span fields recorded for span traces.
+///
+/// A distinct formatter keeps these fields apart from those the outputs format
+/// (which would otherwise reuse them unredacted) & applies the same redaction.
+pub(super) struct SpanTraceFields {
+    #[cfg(feature = "tracing_redact")]
+    pub(super) redactor: Option<super::Redactor>,
+}
+
+impl<'writer> FormatFields<'writer> for SpanTraceFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut visitor = DefaultVisitor::new(writer, true);
+        #[cfg(feature = "tracing_redact")]
+        if let Some(redactor) = &self.redactor {
+            redactor.record(fields, &mut visitor);
+
+            return visitor.finish();
+        }
+
+        fields.record(&mut visitor);
+        visitor.finish()
+    }
+}
+
+/// Enables the spans `EnvFilter` enables & no events, so capturing span traces
+/// doesn't enable events the outputs would discard.
+struct SpanFilter(EnvFilter);
+
+impl<S> Filter<S> for SpanFilter
+where
+    S: Subscriber,
+    EnvFilter: Filter<S>,
+{
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: &Context<'_, S>) -> bool {
+        metadata.is_span() && Filter::enabled(&self.0, metadata, ctx)
+    }
+
+    fn callsite_enabled(&self, metadata: &'static Metadata<'static>) -> Interest {
+        match metadata.is_span() {
+            true => Filter::<S>::callsite_enabled(&self.0, metadata),
+            false => Interest::never(),
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Filter::<S>::max_level_hint(&self.0)
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        Filter::on_new_span(&self.0, attrs, id, ctx);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        Filter::on_record(&self.0, id, values, ctx);
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        Filter::on_enter(&self.0, id, ctx);
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        Filter::on_exit(&self.0, id, ctx);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        Filter::on_close(&self.0, id, ctx);
+    }
+}
+
+/// Formats `trace` root first, matching the span prefix of the text outputs.
+pub(super) fn format_span_trace(trace: &SpanTrace) -> String {
+    let mut spans = Vec::default();
+    trace.with_spans(|metadata, fields| {
+        spans.push(match fields.is_empty() {
+            true => metadata.name().to_string(),
+            false => format!("{}{{{fields}}}", metadata.name()),
+        });
+
+        true
+    });
+    spans.reverse();
+
+    spans.join(":")
+}
+
+/// Logs an error at `ERROR` with its source chain & span context as fields.
+///
+/// The error is recorded as `error`, its sources as `error.sources` & its span
+/// context as `error.spans` (see [`ErrorReport`]). The remaining arguments
+/// accept the same field & message syntax as [`tracing::event!`].
+///
+/// # Example
+///
+/// ```rust
+/// let err = std::io::Error::other("disk full");
+///
+/// toolbox::log_error!(err, path = "/var/data", "Failed to persist state");
+/// ```
+#[macro_export]
+macro_rules! log_error {
+    ($err:expr, $($arg:tt)+) => {{
+        let err: &(dyn ::std::error::Error + 'static) = &$err;
+        let report = $crate::tracing::ErrorReport::new(err);
+
+        ::tracing::error!(
+            error = %report.error,
+            error.sources = ?report.sources,
+            error.spans = %report.spans,
+            $($arg)+
+        );
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::tracing::{CaptureLayer, TracingBuilder};
+
+    #[derive(Debug)]
+    struct Outer(std::io::Error);
+
+    impl fmt::Display for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("Failed to load config")
+        }
+    }
+
+    impl Error for Outer {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    fn load(path: &str) -> Result<(), TracedError<Outer>> {
+        let _span = tracing::info_span!("load", path).entered();
+
+        Err(Outer(std::io::Error::other("permission denied"))).in_current_span()
+    }
+
+    #[test]
+    fn errors_are_logged_with_sources_and_spans() {
+        let (layers, _handle) = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .stdout_default_filter("off")
+            .span_traces("info")
+            .layers()
+            .unwrap();
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(layers)
+            .with(capture.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("startup", attempt = 2).entered();
+            let err = load("/etc/app.toml").unwrap_err();
+
+            let _other = tracing::info_span!("shutdown").entered();
+            log_error!(err, code = 3, "Exiting");
+        });
+
+        let events = capture.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].message.as_deref(), Some("Exiting"));
+        assert_eq!(events[0].field("code"), Some("3"));
+        assert_eq!(events[0].field("error"), Some("Failed to load config"));
+        assert_eq!(events[0].field("error.sources"), Some("[\"permission denied\"]"));
+        assert_eq!(
+            events[0].field("error.spans"),
+            Some("startup{attempt=2}:load{path=\"/etc/app.toml\"}")
+        );
+    }
+
+    #[cfg(feature = "tracing_redact")]
+    #[test]
+    fn span_trace_fields_are_redacted() {
+        use crate::tracing::{LogFormat, RedactionConfig};
+
+        let directory =
+            std::env::temp_dir().join(format!("toolbox-span-traces-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let (layers, handle) = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .stdout_default_filter("off")
+            .log_directory(&directory)
+            .file_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .file_format(LogFormat::Full)
+            .redaction(RedactionConfig::default().field("password"))
+            .span_traces("info")
+            .layers()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span =
+                tracing::info_span!("login", password = "hunter2", user = "alice").entered();
+            tracing::info!("Logging in");
+            log_error!(load("/etc/app.toml").unwrap_err(), "Login failed");
+        });
+        handle.shutdown();
+
+        let logs: String = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        assert!(logs.contains("Logging in"));
+        assert!(logs.contains("Login failed"));
+        assert!(logs.contains("password=[REDACTED]"));
+        assert!(logs.contains("user=\"alice\""));
+        assert!(!logs.contains("hunter2"), "{logs}");
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn span_traces_only_enable_spans() {
+        let (layers, _handle) = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .stdout_default_filter("off")
+            .span_traces("info")
+            .layers()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(!tracing::enabled!(tracing::Level::ERROR));
+            assert!(!tracing::info_span!("traced").is_disabled());
+            assert!(tracing::debug_span!("untraced").is_disabled());
+        });
+    }
+
+    #[test]
+    fn uninstrumented_errors_use_current_spans() {
+        let subscriber = tracing_subscriber::registry().with(tracing_error::ErrorLayer::default());
+
+        let report = tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("request").entered();
+
+            ErrorReport::new(&std::io::Error::other("timed out"))
+        });
+
+        assert_eq!(
+            report,
+            ErrorReport {
+                error: "timed out".to_string(),
+                sources: Vec::default(),
+                spans: "request".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::Deserialize;
+use tracing_subscriber::filter::LevelFilter;
+
+use super::{parse_filter, TracingError};
+
+/// Per-output filters read from a TOML or JSON file.
+///
+/// ```toml
+/// [stdout]
+/// level = "info"
+///
+/// [file]
+/// level = "info"
+/// modules = { my_crate = "debug", hyper = "warn" }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FilterConfig {
+    stdout: Option<OutputFilter>,
+    file: Option<OutputFilter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OutputFilter {
+    /// The level of events not matched by `modules`.
+    level: Option<String>,
+    /// Per-module (or target) level overrides.
+    #[serde(default)]
+    modules: BTreeMap<String, String>,
+}
+
+/// Parses a filter config, returning the validated directives keyed by output
+/// name (`stdout` or `file`).
+pub(super) fn parse_filter_config(
+    path: &Path,
+    extension: &str,
+    contents: &str,
+) -> Result<HashMap<String, String>, TracingError> {
+    let error = |err: String| TracingError::FilterConfig { path: path.to_path_buf(), err };
+
+    let config: FilterConfig = match extension {
+        "toml" => toml::from_str(contents).map_err(|err| error(err.message().to_string()))?,
+        _ => serde_json::from_str(contents).map_err(|err| error(err.to_string()))?,
+    };
+
+    [("stdout", config.stdout), ("file", config.file)]
+        .into_iter()
+        .filter_map(|(output, filter)| Some((output, filter?)))
+        .map(|(output, filter)| {
+            let directives = filter.directives(output).map_err(error)?;
+            parse_filter(output, &directives)?;
+
+            Ok((output.to_string(), directives))
+        })
+        .collect()
+}
+
+impl OutputFilter {
+    /// Converts the filter into `EnvFilter` directives, validating each level.
+    fn directives(&self, output: &str) -> Result<String, String> {
+        let validate = |key: String, level: &str| match level.parse::<LevelFilter>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(format!("Invalid level; key={key}; level={level}")),
+        };
+
+        let mut directives = Vec::default();
+        if let Some(level) = &self.level {
+            validate(format!("{output}.level"), level)?;
+            directives.push(level.clone());
+        }
+        for (module, level) in &self.modules {
+            validate(format!("{output}.modules.{module}"), level)?;
+            directives.push(format!("{module}={level}"));
+        }
+
+        Ok(directives.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_and_json_are_equivalent() {
+        let path = Path::new("filters");
+        let toml = [
+            "[stdout]",
+            "level = \"warn\"",
+            "",
+            "[file]",
+            "modules = { my_crate = \"debug\", hyper = \"off\" }",
+        ]
+        .join("\n");
+        let json = r#"{"stdout": {"level": "warn"}, "file": {"modules": {"my_crate": "debug", "hyper": "off"}}}"#;
+
+        let expected = HashMap::from([
+            ("stdout".to_string(), "warn".to_string()),
+            ("file".to_string(), "hyper=off,my_crate=debug".to_string()),
+        ]);
+        assert_eq!(parse_filter_config(path, "toml", &toml).unwrap(), expected);
+        assert_eq!(parse_filter_config(path, "json", json).unwrap(), expected);
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        let path = Path::new("filters.toml");
+        let err = |contents: &str| {
+            parse_filter_config(path, "toml", contents)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            err("[stdout.modules]\nmy_crate = \"loud\""),
+            "Invalid filter config; path=filters.toml; err=Invalid level; \
+             key=stdout.modules.my_crate; level=loud"
+        );
+        assert!(err("[stderr]\nlevel = \"info\"")
+            .starts_with("Invalid filter config; path=filters.toml; err=unknown field `stderr`"));
+        assert!(err("[file.modules]\n\"a b\" = \"info\"").starts_with("Invalid filter; var=file"));
+    }
+}
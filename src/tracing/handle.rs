@@ -0,0 +1,163 @@
+#[cfg(feature = "shutdown")]
+use std::process::ExitCode;
+
+use tracing_appender::non_blocking::{ErrorCounter, WorkerGuard};
+
+use super::{dropped_lines, PeriodicReporter, ReloadHandle};
+#[cfg(feature = "shutdown")]
+use crate::shutdown::Shutdown;
+
+/// Keeps the installed subscriber's outputs alive & exposes runtime controls.
+///
+/// Owns the background writers & exporters, dropping the handle flushes &
+/// stops them. Hold it for as long as logs should be written & prefer
+/// [`TracingHandle::shutdown`] to make the final flush explicit.
+#[derive(Debug)]
+#[must_use = "Dropping the handle stops writing logs to file"]
+pub struct TracingHandle {
+    // Fields drop in declaration order, so the reporters' final reports are
+    // emitted before the guards flush & close the outputs.
+    pub(super) reporters: Vec<PeriodicReporter>,
+    pub(super) guards: Vec<WorkerGuard>,
+    pub(super) counters: Vec<ErrorCounter>,
+    pub(super) reload: ReloadHandle,
+    #[cfg(all(unix, feature = "tracing_sighup"))]
+    pub(super) sighup: Option<super::SighupReloader>,
+    #[cfg(feature = "tracing_otlp")]
+    pub(super) otlp: Option<super::OtlpGuard>,
+}
+
+impl TracingHandle {
+    /// Handle for swapping log filters at runtime.
+    #[must_use]
+    pub fn reload_handle(&self) -> &ReloadHandle {
+        &self.reload
+    }
+
+    /// Total lines dropped by the background writers due to full buffers.
+    #[must_use]
+    pub fn dropped_lines(&self) -> usize {
+        dropped_lines(&self.counters)
+    }
+
+    /// Flushes all buffered logs & stops the background writers & exporters.
+    ///
+    /// Events emitted afterwards are only written to stderr.
+    pub fn shutdown(self) {
+        let TracingHandle {
+            reporters,
+            guards,
+            counters: _,
+            reload: _,
+            #[cfg(all(unix, feature = "tracing_sighup"))]
+            sighup,
+            #[cfg(feature = "tracing_otlp")]
+            otlp,
+        } = self;
+
+        #[cfg(all(unix, feature = "tracing_sighup"))]
+        drop(sighup);
+        // Run the final reports while the outputs are still alive.
+        drop(reporters);
+        // Dropping the guards blocks until their buffered lines are written.
+        drop(guards);
+        #[cfg(feature = "tracing_otlp")]
+        drop(otlp);
+    }
+
+    /// Blocks until `shutdown` is triggered, logs the shutdown summary, then
+    /// flushes all outputs.
+    ///
+    /// Returns the exit code implied by the shutdown reason, intended to be
+    /// returned from `main`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::process::ExitCode;
+    ///
+    /// use toolbox::shutdown::Shutdown;
+    /// use toolbox::tracing::setup_tracing;
+    ///
+    /// fn main() -> ExitCode {
+    ///     let tracing = setup_tracing("my-crate", None);
+    ///     let shutdown = Shutdown::new();
+    ///
+    ///     // Spawn the app, passing it a clone of `shutdown`...
+    ///
+    ///     tracing.wait_for_shutdown(&shutdown)
+    /// }
+    /// ```
+    #[cfg(feature = "shutdown")]
+    pub fn wait_for_shutdown(self, shutdown: &Shutdown) -> ExitCode {
+        shutdown.wait();
+        shutdown.log_summary();
+        self.shutdown();
+
+        shutdown.exit_code()
+    }
+}
+
+#[cfg(all(test, feature = "shutdown"))]
+mod tests {
+    use std::io::Write;
+    use std::process::ExitCode;
+    use std::time::Duration;
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::shutdown::{Shutdown, ShutdownReason};
+    use crate::tracing::{PeriodicReporter, TracingBuilder};
+
+    #[test]
+    fn final_logs_are_written_on_shutdown() {
+        let directory = std::env::temp_dir().join(format!("toolbox-handle-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let (layers, handle) = TracingBuilder::new("tests")
+            .log_directory(&directory)
+            .file_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .layers()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+
+        let shutdown = Shutdown::new();
+        let trigger = shutdown.clone();
+        std::thread::spawn(move || {
+            trigger.shutdown_with(ShutdownReason::Error("disk full".to_string()));
+        });
+        let exit_code =
+            tracing::subscriber::with_default(subscriber, || handle.wait_for_shutdown(&shutdown));
+
+        assert_eq!(exit_code, ExitCode::FAILURE);
+        let logs: String = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        assert!(logs.contains("Shutdown summary"));
+        assert!(logs.contains("disk full"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn final_reports_are_written_on_drop() {
+        let path = std::env::temp_dir().join(format!("toolbox-handle-drop-{}", std::process::id()));
+        let (writer, guard) = tracing_appender::non_blocking(std::fs::File::create(&path).unwrap());
+        let mut writer = writer;
+        let reporter = PeriodicReporter::spawn("test-reporter", Duration::MAX, move || {
+            let _ = writer.write_all(b"Final report\n");
+        })
+        .unwrap();
+        let (_, mut handle) = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .layers()
+            .unwrap();
+        handle.reporters.push(reporter);
+        handle.guards.push(guard);
+
+        drop(handle);
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Final report\n");
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,192 @@
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use super::capture::FieldVisitor;
+use super::syslog::severity;
+use super::{parse_filter, BoxedLayer, TracingError};
+
+/// Sends events to journald using its native protocol.
+///
+/// Event fields are sent as journal fields, upper-cased with invalid
+/// characters replaced by `_` (e.g. `peer.addr` becomes `PEER_ADDR`). Fields
+/// clashing with the fields set by this output (e.g. `message`) are prefixed
+/// with `F_`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use toolbox::tracing::{JournaldConfig, TracingBuilder};
+///
+/// let _handle = TracingBuilder::new("my-crate")
+///     .journald(JournaldConfig::new("my-crate"))
+///     .init()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct JournaldConfig {
+    syslog_identifier: String,
+    socket: PathBuf,
+    filter: String,
+}
+
+impl JournaldConfig {
+    /// Creates a config logging as `syslog_identifier`.
+    pub fn new(syslog_identifier: impl Into<String>) -> Self {
+        JournaldConfig {
+            syslog_identifier: syslog_identifier.into(),
+            socket: PathBuf::from("/run/systemd/journal/socket"),
+            filter: "info".to_string(),
+        }
+    }
+
+    /// The journald socket, defaults to `/run/systemd/journal/socket`.
+    pub fn socket(mut self, socket: impl Into<PathBuf>) -> Self {
+        self.socket = socket.into();
+
+        self
+    }
+
+    /// Filter directives for this output, defaults to `info`.
+    pub fn filter(mut self, directives: impl Into<String>) -> Self {
+        self.filter = directives.into();
+
+        self
+    }
+
+    pub(super) fn layer(&self) -> Result<BoxedLayer, TracingError> {
+        let filter = parse_filter("journald", &self.filter)?;
+        let socket = UnixDatagram::unbound().map_err(TracingError::Output)?;
+        socket.connect(&self.socket).map_err(TracingError::Output)?;
+
+        Ok(JournaldLayer { socket, syslog_identifier: self.syslog_identifier.clone() }
+            .with_filter(filter)
+            .boxed())
+    }
+}
+
+struct JournaldLayer {
+    socket: UnixDatagram,
+    syslog_identifier: String,
+}
+
+impl<S> Layer<S> for JournaldLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+
+        let mut payload = Vec::default();
+        put_field(&mut payload, "PRIORITY", &severity(metadata.level()).to_string());
+        put_field(&mut payload, "SYSLOG_IDENTIFIER", &self.syslog_identifier);
+        put_field(&mut payload, "MESSAGE", visitor.message.as_deref().unwrap_or_default());
+        put_field(&mut payload, "TARGET", metadata.target());
+        if let Some(file) = metadata.file() {
+            put_field(&mut payload, "CODE_FILE", file);
+        }
+        if let Some(line) = metadata.line() {
+            put_field(&mut payload, "CODE_LINE", &line.to_string());
+        }
+        if let Some(scope) = ctx.event_scope(event) {
+            let spans: Vec<_> = scope.from_root().map(|span| span.name()).collect();
+            put_field(&mut payload, "SPANS", &spans.join(":"));
+        }
+        for (name, value) in &visitor.fields {
+            put_field(&mut payload, &field_name(name), value);
+        }
+
+        // Delivery is best effort, there is nowhere to report failures to.
+        let _ = self.socket.send(&payload);
+    }
+}
+
+/// Appends a field using the native protocol, values containing newlines are
+/// length prefixed.
+fn put_field(payload: &mut Vec<u8>, name: &str, value: &str) {
+    payload.extend_from_slice(name.as_bytes());
+    match value.contains('\n') {
+        true => {
+            payload.push(b'\n');
+            payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        }
+        false => payload.push(b'='),
+    }
+    payload.extend_from_slice(value.as_bytes());
+    payload.push(b'\n');
+}
+
+/// The fields set for every event, which event fields must not overwrite.
+const RESERVED_FIELDS: [&str; 7] =
+    ["PRIORITY", "SYSLOG_IDENTIFIER", "MESSAGE", "TARGET", "CODE_FILE", "CODE_LINE", "SPANS"];
+
+/// Converts `name` into a valid journal field name.
+fn field_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|char| match char.is_ascii_alphanumeric() {
+            true => char.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect();
+
+    // Field names must start with a letter.
+    match name.starts_with(|char: char| char.is_ascii_uppercase()) {
+        true if RESERVED_FIELDS.contains(&name.as_str()) => format!("F_{name}"),
+        true => name,
+        false => format!("F{name}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn sends_native_protocol() {
+        let path = std::env::temp_dir().join(format!("toolbox-journald-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        let layer = JournaldConfig::new("toolbox")
+            .socket(&path)
+            .layer()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        let line = line!() + 3;
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("request").in_scope(|| {
+                tracing::error!(peer.addr = "1.2.3.4", _kind = "a\nb", priority = 5, "Failed");
+            });
+        });
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let mut expected = [
+            "PRIORITY=3".to_string(),
+            "SYSLOG_IDENTIFIER=toolbox".to_string(),
+            "MESSAGE=Failed".to_string(),
+            "TARGET=toolbox::tracing::journald::tests".to_string(),
+            format!("CODE_FILE={}", file!()),
+            format!("CODE_LINE={line}"),
+            "SPANS=request".to_string(),
+            "F_KIND\n".to_string(),
+        ]
+        .join("\n")
+        .into_bytes();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\nPEER_ADDR=1.2.3.4\nF_PRIORITY=5\n");
+        assert_eq!(&buf[..len], expected);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+/// Configurable tracing subscriber setup.
+mod builder;
+/// Event capture for asserting on logs in tests.
+mod capture;
+/// Reporting of lines dropped by the non-blocking writers.
+mod dropped_lines;
+/// Error reports carrying span context.
+#[cfg(feature = "tracing_error")]
+mod error;
+/// Filters loaded from TOML or JSON config files.
+#[cfg(feature = "tracing_config")]
+mod filter_config;
+/// Handle returned by the tracing setup.
+mod handle;
+/// Journald output using the native protocol.
+#[cfg(unix)]
+mod journald;
+/// OpenTelemetry span export.
+#[cfg(feature = "tracing_otlp")]
+mod otlp;
+/// Panic hook that reports panics via tracing.
+mod panic;
+/// Rate limited logging macros.
+mod rate_limit;
+/// Redaction of secrets from formatted fields.
+#[cfg(feature = "tracing_redact")]
+mod redact;
+/// Runtime reloading of log filters.
+mod reload;
+/// Background thread running periodic reports.
+mod reporter;
+/// Log file writer with rotation & retention.
+mod rolling;
+/// Sampling of events from noisy targets.
+mod sampling;
+/// RFC 5424 syslog output.
+mod syslog;
+/// Span timing & slow span detection.
+mod timing;
+
+use std::path::Path;
+
+pub use builder::*;
+pub use capture::*;
+use dropped_lines::*;
+#[cfg(feature = "tracing_error")]
+pub use error::*;
+#[cfg(feature = "tracing_config")]
+use filter_config::*;
+pub use handle::*;
+#[cfg(unix)]
+pub use journald::*;
+#[cfg(feature = "tracing_otlp")]
+pub use otlp::*;
+pub use panic::*;
+pub use rate_limit::*;
+#[cfg(feature = "tracing_redact")]
+pub use redact::*;
+pub use reload::*;
+use reporter::*;
+pub use rolling::*;
+pub use sampling::*;
+pub use syslog::*;
+pub use timing::*;
+
+/// Installs the global tracing subscriber with the default configuration.
+///
+/// The returned handle must be held for as long as logs should be written to
+/// file, see [`TracingHandle`]. See [`TracingBuilder`] for a configurable (&
+/// non-panicking) alternative.
+///
+/// # Panics
+///
+/// If either filter env var is invalid, the log file cannot be created, or a
+/// global subscriber has already been set.
+pub fn setup_tracing(pkg_name: &str, log_directory: Option<&Path>) -> TracingHandle {
+    let mut builder = TracingBuilder::new(pkg_name);
+    if let Some(log_directory) = log_directory {
+        builder = builder.log_directory(log_directory);
+    }
+
+    builder
+        .init()
+        .unwrap_or_else(|err| panic!("Failed to setup tracing; err={err}"))
+}
+
+#[macro_export]
+macro_rules! error_once {
+    () => {
+        $crate::error_once!("Explicit error")
+    };
+    ($($arg:tt)+) => {{
+        static CALLSITE: $crate::soft_assert::Callsite =
+            $crate::soft_assert::Callsite::new(file!(), line!(), "false");
+
+        if CALLSITE.violated($crate::soft_assert::ReportPolicy::Once, cfg!(test), || {
+            $crate::tracing::render_event(|| ::tracing::error!($($arg)+))
+        }) {
+            ::tracing::error!(file = file!(), line = line!(), $($arg)+);
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! assert_once {
+    ($condition:expr $(,)?) => {{
+        static CALLSITE: $crate::soft_assert::Callsite =
+            $crate::soft_assert::Callsite::new(file!(), line!(), stringify!($condition));
+
+        if !$condition
+            && CALLSITE.violated($crate::soft_assert::ReportPolicy::Once, cfg!(test), || None)
+        {
+            ::tracing::error!(file = file!(), line = line!(), "Assertion violated");
+        }
+    }};
+    ($condition:expr, $($arg:tt)+) => {{
+        static CALLSITE: $crate::soft_assert::Callsite =
+            $crate::soft_assert::Callsite::new(file!(), line!(), stringify!($condition));
+
+        if !$condition
+            && CALLSITE.violated($crate::soft_assert::ReportPolicy::Once, cfg!(test), || {
+                $crate::tracing::render_event(|| ::tracing::error!($($arg)+))
+            })
+        {
+            ::tracing::error!(file = file!(), line = line!(), $($arg)+);
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! assert_eq_once {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__assert_cmp_once!($left, ==, $right)
+    };
+}
+
+#[macro_export]
+macro_rules! assert_ne_once {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::__assert_cmp_once!($left, !=, $right)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_cmp_once {
+    ($left:expr, $op:tt, $right:expr) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                static CALLSITE: $crate::soft_assert::Callsite = $crate::soft_assert::Callsite::new(
+                    file!(),
+                    line!(),
+                    stringify!($left $op $right),
+                );
+
+                if !(*left $op *right)
+                    && CALLSITE.violated($crate::soft_assert::ReportPolicy::Once, cfg!(test), || {
+                        Some(format!("left={left:?}; right={right:?}"))
+                    })
+                {
+                    ::tracing::error!(
+                        file = file!(),
+                        line = line!(),
+                        left = ?left,
+                        right = ?right,
+                        "Assertion violated"
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! assert_matches_once {
+    ($value:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {
+        match $value {
+            $pattern $(if $guard)? => {}
+            ref value => {
+                static CALLSITE: $crate::soft_assert::Callsite = $crate::soft_assert::Callsite::new(
+                    file!(),
+                    line!(),
+                    concat!(
+                        "matches!(",
+                        stringify!($value),
+                        ", ",
+                        stringify!($pattern $(if $guard)?),
+                        ")"
+                    ),
+                );
+
+                if CALLSITE.violated($crate::soft_assert::ReportPolicy::Once, cfg!(test), || {
+                    Some(format!("value={value:?}"))
+                }) {
+                    ::tracing::error!(
+                        file = file!(),
+                        line = line!(),
+                        value = ?value,
+                        "Assertion violated"
+                    );
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use ::tracing::Level;
+
+    use super::*;
+    use crate::soft_assert::{capture, with_mode, Mode};
+
+    #[test]
+    fn error_once_fires() {
+        let line = line!() + 4;
+        let events = capture_events(|| {
+            with_mode(Mode::Soft, || {
+                for _ in 0..3 {
+                    error_once!();
+                }
+            });
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, Level::ERROR);
+        assert_eq!(events[0].message.as_deref(), Some("Explicit error"));
+        assert_eq!(events[0].field("file"), Some(file!()));
+        assert_eq!(events[0].field("line"), Some(line.to_string().as_str()));
+    }
+
+    #[test]
+    fn assert_once_fires() {
+        let line = line!() + 4;
+        let events = capture_events(|| {
+            with_mode(Mode::Soft, || {
+                for _ in 0..3 {
+                    assert_once!(false);
+                }
+            });
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, Level::ERROR);
+        assert_eq!(events[0].message.as_deref(), Some("Assertion violated"));
+        assert_eq!(events[0].field("file"), Some(file!()));
+        assert_eq!(events[0].field("line"), Some(line.to_string().as_str()));
+    }
+
+    #[test]
+    fn once_macros_accept_fields() {
+        let (x, y) = (3, 2);
+        let events = capture_events(|| {
+            with_mode(Mode::Soft, || {
+                assert_once!(x < y, x, y, "Queue overflow");
+                error_once!(peer = "a", "Failed to connect; attempt={}", 2);
+            });
+        });
+
+        let events: Vec<_> = events
+            .iter()
+            .map(|event| (event.message.as_deref().unwrap(), event.field("x"), event.field("peer")))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("Queue overflow", Some("3"), None),
+                ("Failed to connect; attempt=2", None, Some("a")),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Soft assert violated; condition=x < y; msg=Queue overflow; x=3; y=2")]
+    fn assert_once_with_fields_panics_in_tests() {
+        let (x, y) = (3, 2);
+        assert_once!(x < y, x, y, "Queue overflow");
+    }
+
+    #[test]
+    fn assert_once_does_not_fire() {
+        let events = capture_events(|| assert_once!(true));
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn assert_once_counts_violations() {
+        let line = line!() + 3;
+        let violations = capture(|| {
+            for i in 0..5 {
+                assert_once!(i > 2);
+            }
+        });
+        assert_eq!(violations.len(), 3);
+
+        let record = crate::soft_assert::violations()
+            .into_iter()
+            .find(|record| record.file == file!() && record.line == line)
+            .unwrap();
+        assert_eq!(record.condition, "i > 2");
+        assert_eq!(record.count, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Soft assert violated; condition=1 > 2")]
+    fn assert_once_panics_in_tests() {
+        assert_once!(1 > 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Soft assert violated; condition=false; msg=Explicit error")]
+    fn error_once_panics_in_tests() {
+        error_once!();
+    }
+
+    #[test]
+    fn comparison_once_macros() {
+        let violations = capture(|| {
+            assert_eq_once!(1, 1);
+            assert_eq_once!(1, 2);
+            assert_ne_once!(1, 2);
+            assert_ne_once!("a", "a");
+            assert_matches_once!(Some(1), Some(_));
+            assert_matches_once!(None::<u8>, Some(_));
+        });
+
+        let violations: Vec<_> = violations
+            .iter()
+            .map(|violation| (violation.condition, violation.message.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                ("1 == 2", "left=1; right=2"),
+                ("\"a\" != \"a\"", "left=\"a\"; right=\"a\""),
+                ("matches!(None::<u8>, Some(_))", "value=None"),
+            ]
+        );
+    }
+
+    #[test]
+    fn comparison_once_macros_fire() {
+        let events = capture_events(|| {
+            with_mode(Mode::Soft, || {
+                assert_eq_once!(1, 2);
+                assert_ne_once!(1, 1);
+                assert_matches_once!(Err::<(), _>(5), Ok(()));
+            });
+        });
+
+        let fields: Vec<_> = events
+            .iter()
+            .map(|event| (event.field("left"), event.field("right"), event.field("value")))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (Some("1"), Some("2"), None),
+                (Some("1"), Some("1"), None),
+                (None, None, Some("Err(5)")),
+            ]
+        );
+    }
+}
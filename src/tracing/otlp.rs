@@ -0,0 +1,213 @@
+use opentelemetry::trace::TracerProvider;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing_subscriber::Layer;
+
+use super::{parse_filter, BoxedLayer, TracingError};
+
+/// Exports spans to an OpenTelemetry collector over OTLP/HTTP.
+///
+/// Exported spans are not redacted (see `TracingBuilder::redaction`).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use toolbox::tracing::{OtlpConfig, TracingBuilder};
+///
+/// let _handle = TracingBuilder::new("my-crate")
+///     .otlp(
+///         OtlpConfig::new("my-service")
+///             .endpoint("http://collector:4318/v1/traces")
+///             .service_version(toolbox::version!())
+///             .resource_attribute("deployment.environment", "prod"),
+///     )
+///     .init()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct OtlpConfig {
+    service_name: String,
+    endpoint: String,
+    resource_attributes: Vec<(String, String)>,
+    filter: String,
+}
+
+impl OtlpConfig {
+    /// Creates a config exporting as `service_name` to the default local
+    /// collector endpoint (`http://localhost:4318/v1/traces`).
+    pub fn new(service_name: impl Into<String>) -> Self {
+        OtlpConfig {
+            service_name: service_name.into(),
+            endpoint: "http://localhost:4318/v1/traces".to_string(),
+            resource_attributes: Vec::default(),
+            filter: "info".to_string(),
+        }
+    }
+
+    /// The collector's OTLP/HTTP traces endpoint.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into();
+
+        self
+    }
+
+    /// Adds a resource attribute attached to all exported spans.
+    pub fn resource_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.resource_attributes.push((key.into(), value.into()));
+
+        self
+    }
+
+    /// Sets the `service.version` resource attribute, typically to the output
+    /// of `toolbox::version!()`.
+    pub fn service_version(self, version: impl Into<String>) -> Self {
+        self.resource_attribute("service.version", version)
+    }
+
+    /// Filter directives for the exported spans, defaults to `info`.
+    pub fn filter(mut self, directives: impl Into<String>) -> Self {
+        self.filter = directives.into();
+
+        self
+    }
+
+    pub(super) fn layer(&self) -> Result<(BoxedLayer, OtlpGuard), TracingError> {
+        let filter = parse_filter("otlp", &self.filter)?;
+
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .with_endpoint(&self.endpoint)
+            .build()?;
+        let resource = Resource::builder()
+            .with_service_name(self.service_name.clone())
+            .with_attributes(
+                self.resource_attributes
+                    .iter()
+                    .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+            )
+            .build();
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build();
+
+        let layer = tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(self.service_name.clone()))
+            .with_filter(filter)
+            .boxed();
+
+        Ok((layer, OtlpGuard { provider }))
+    }
+}
+
+/// Flushes & shuts down the OTLP exporter when dropped.
+#[derive(Debug)]
+pub struct OtlpGuard {
+    provider: SdkTracerProvider,
+}
+
+impl Drop for OtlpGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.provider.shutdown() {
+            eprintln!("Failed to shutdown OTLP exporter; err={err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    /// Accepts OTLP/HTTP requests, forwarding each request body.
+    fn mock_collector() -> (String, mpsc::Receiver<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+
+                let mut body = vec![0; content_length];
+                stream.read_exact(&mut body).unwrap();
+                // Hand over the body before replying, as the exporter returns once
+                // it sees the response.
+                let _ = tx.send(body);
+                stream
+                    .get_mut()
+                    .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                    .unwrap();
+            }
+        });
+
+        (endpoint, rx)
+    }
+
+    #[test]
+    fn spans_are_exported_on_drop() {
+        let (endpoint, requests) = mock_collector();
+        let (layer, guard) = OtlpConfig::new("toolbox-tests")
+            .endpoint(endpoint)
+            .service_version("1.2.3 (abcdef0)")
+            .layer()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("exported_span").in_scope(|| tracing::info!("Inside span"));
+        });
+        drop(guard);
+
+        let body = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        let contains = |needle: &[u8]| body.windows(needle.len()).any(|window| window == needle);
+        assert!(contains(b"exported_span"));
+        assert!(contains(b"toolbox-tests"));
+        assert!(contains(b"1.2.3 (abcdef0)"));
+    }
+
+    #[test]
+    #[cfg(feature = "tracing_redact")]
+    fn spans_are_exported_unredacted() {
+        let (endpoint, requests) = mock_collector();
+        let (layers, handle) = crate::tracing::TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .stdout_default_filter("off")
+            .otlp(OtlpConfig::new("toolbox-tests").endpoint(endpoint))
+            .redaction(crate::tracing::RedactionConfig::default().field("password"))
+            .layers()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("login", password = "hunter2").in_scope(|| {});
+        });
+        handle.shutdown();
+
+        let body = requests.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(body.windows(7).any(|window| window == b"hunter2"));
+    }
+}
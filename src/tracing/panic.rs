@@ -0,0 +1,179 @@
+use std::backtrace::Backtrace;
+use std::panic::PanicHookInfo;
+
+use tracing::Level;
+
+#[cfg(feature = "shutdown")]
+use crate::shutdown::{Shutdown, ShutdownReason};
+
+/// A panic hook that logs panics through `tracing`.
+///
+/// Each panic is logged at `ERROR` with the panic message as `payload`, the
+/// location, thread name & a captured backtrace (& with the `tracing_error`
+/// feature, the spans the panic occurred in as `spans`, see
+/// `TracingBuilder::span_traces`). If a `Shutdown` is attached (requires the
+/// `shutdown` feature), the panic additionally triggers shutdown & is recorded
+/// as the shutdown reason.
+///
+/// Panics are passed on to the previously installed hook (by default, printing
+/// to stderr) instead when `ERROR` events aren't enabled, e.g. before the
+/// subscriber is installed, so they're never silently dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use toolbox::tracing::PanicHook;
+///
+/// PanicHook::default().install();
+/// ```
+#[derive(Debug, Default)]
+pub struct PanicHook {
+    #[cfg(feature = "shutdown")]
+    shutdown: Option<Shutdown>,
+}
+
+impl PanicHook {
+    /// Triggers `shutdown` with [`ShutdownReason::Panic`] whenever a panic is
+    /// reported.
+    #[cfg(feature = "shutdown")]
+    #[must_use]
+    pub fn shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = Some(shutdown);
+
+        self
+    }
+
+    /// Replaces the current panic hook with this hook, falling back to the
+    /// replaced hook while `ERROR` events aren't enabled.
+    pub fn install(self) {
+        let previous = std::panic::take_hook();
+
+        std::panic::set_hook(Box::new(move |info| self.handle(info, &previous)));
+    }
+
+    fn handle(&self, info: &PanicHookInfo<'_>, previous: &dyn Fn(&PanicHookInfo<'_>)) {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(ToString::to_string)
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        let location = info.location().map(|location| {
+            format!("{}:{}:{}", location.file(), location.line(), location.column())
+        });
+        let thread = std::thread::current();
+
+        #[cfg(feature = "tracing_error")]
+        let spans = super::format_span_trace(&super::SpanTrace::capture());
+        #[cfg(not(feature = "tracing_error"))]
+        let spans = tracing::field::Empty;
+
+        match tracing::enabled!(Level::ERROR) {
+            true => tracing::error!(
+                payload = %message,
+                location,
+                thread = thread.name().unwrap_or("<unnamed>"),
+                spans,
+                backtrace = %Backtrace::force_capture(),
+                "Panicked"
+            ),
+            false => previous(info),
+        }
+
+        #[cfg(feature = "shutdown")]
+        if let Some(shutdown) = &self.shutdown {
+            shutdown.shutdown_with(ShutdownReason::Panic { message, location });
+        }
+    }
+}
+
+#[cfg(all(test, feature = "shutdown"))]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex, PoisonError};
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::tracing::CaptureLayer;
+
+    const THREAD: &str = "toolbox-panic-hook-test";
+
+    /// Installs `hook` for panics on [`THREAD`] only, as other tests panic
+    /// concurrently, recording whether it fell back to the previous hook.
+    fn with_hook(hook: PanicHook, fell_back: Arc<AtomicBool>, f: impl FnOnce()) {
+        static LOCK: Mutex<()> = Mutex::new(());
+        let _lock = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let previous = Arc::new(std::panic::take_hook());
+        let forward = previous.clone();
+        std::panic::set_hook(Box::new(move |info| {
+            match std::thread::current().name() == Some(THREAD) {
+                true => hook.handle(info, &|_| fell_back.store(true, Ordering::Relaxed)),
+                false => forward(info),
+            }
+        }));
+
+        f();
+
+        drop(std::panic::take_hook());
+        std::panic::set_hook(Arc::into_inner(previous).unwrap());
+    }
+
+    fn panic_on_thread(f: impl FnOnce() + Send + 'static) {
+        let result = std::thread::Builder::new()
+            .name(THREAD.to_string())
+            .spawn(f)
+            .unwrap()
+            .join();
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn panic_triggers_shutdown() {
+        let shutdown = Shutdown::new();
+        let fell_back = Arc::new(AtomicBool::new(false));
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+
+        let line = line!() + 4;
+        let hook = PanicHook::default().shutdown(shutdown.clone());
+        with_hook(hook, fell_back.clone(), || {
+            panic_on_thread(|| {
+                tracing::subscriber::with_default(subscriber, || panic!("boom"));
+            });
+        });
+
+        assert!(!fell_back.load(Ordering::Relaxed));
+        let events = capture.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].message.as_deref(), Some("Panicked"));
+        assert_eq!(events[0].field("payload"), Some("boom"));
+        assert_eq!(events[0].field("thread"), Some(THREAD));
+        assert_eq!(
+            shutdown.reason(),
+            Some(&ShutdownReason::Panic {
+                message: "boom".to_string(),
+                location: Some(format!("{}:{line}:66", file!())),
+            })
+        );
+    }
+
+    #[test]
+    fn panics_fall_back_without_subscriber() {
+        let shutdown = Shutdown::new();
+        let fell_back = Arc::new(AtomicBool::new(false));
+
+        let hook = PanicHook::default().shutdown(shutdown.clone());
+        with_hook(hook, fell_back.clone(), || {
+            panic_on_thread(|| {
+                let subscriber = tracing::subscriber::NoSubscriber::default();
+                tracing::subscriber::with_default(subscriber, || panic!("boom"));
+            });
+        });
+
+        assert!(fell_back.load(Ordering::Relaxed));
+        assert!(shutdown.is_shutdown());
+    }
+}
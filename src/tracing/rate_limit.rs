@@ -0,0 +1,289 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::soft_assert::{monotonic_nanos, ReportPolicy};
+
+/// Rate limiting state for a single logging callsite.
+///
+/// Each macro invocation owns a `static` limiter.
+#[doc(hidden)]
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    count: AtomicU64,
+    suppressed: AtomicU64,
+    last_emitted: AtomicU64,
+}
+
+impl RateLimiter {
+    #[must_use]
+    pub const fn new() -> Self {
+        RateLimiter {
+            count: AtomicU64::new(0),
+            suppressed: AtomicU64::new(0),
+            last_emitted: AtomicU64::new(0),
+        }
+    }
+
+    /// Records an occurrence, returning the number of occurrences suppressed
+    /// since the last emission if this occurrence should be emitted.
+    pub fn check(&self, policy: ReportPolicy) -> Option<u64> {
+        let previous = self.count.fetch_add(1, Ordering::Relaxed);
+
+        match policy.should_report(previous, monotonic_nanos(), &self.last_emitted) {
+            true => Some(self.suppressed.swap(0, Ordering::Relaxed)),
+            false => {
+                self.suppressed.fetch_add(1, Ordering::Relaxed);
+
+                None
+            }
+        }
+    }
+}
+
+/// Logs an event at most once per interval.
+///
+/// Accepts the same `target:`, `parent:`, field & message syntax as
+/// [`tracing::event!`], each emitted event carries a `suppressed` field
+/// counting the events skipped since the previous emission.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use tracing::Level;
+///
+/// toolbox::log_every!(Level::WARN, Duration::from_secs(10), peer = 3, "Slow peer");
+/// ```
+#[macro_export]
+macro_rules! log_every {
+    ($level:expr, $interval:expr, $($arg:tt)+) => {
+        $crate::__log_rate_limited!(
+            $level,
+            $crate::soft_assert::ReportPolicy::Every($interval),
+            $($arg)+
+        )
+    };
+}
+
+/// Logs the 1st, (N+1)th, (2N+1)th, ... occurrence of an event.
+///
+/// See [`log_every!`] for the accepted syntax.
+#[macro_export]
+macro_rules! log_every_n {
+    ($level:expr, $n:expr, $($arg:tt)+) => {
+        $crate::__log_rate_limited!($level, $crate::soft_assert::ReportPolicy::EveryN($n), $($arg)+)
+    };
+}
+
+/// Logs only the first occurrence of an event.
+///
+/// Accepts the same `target:`, `parent:`, field & message syntax as
+/// [`tracing::event!`].
+#[macro_export]
+macro_rules! log_once {
+    ($level:expr, $($arg:tt)+) => {{
+        static LIMITER: $crate::tracing::RateLimiter = $crate::tracing::RateLimiter::new();
+
+        if LIMITER
+            .check($crate::soft_assert::ReportPolicy::Once)
+            .is_some()
+        {
+            $crate::__log_event!($level, {}, $($arg)+);
+        }
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_rate_limited {
+    ($level:expr, $policy:expr, $($arg:tt)+) => {{
+        static LIMITER: $crate::tracing::RateLimiter = $crate::tracing::RateLimiter::new();
+
+        if let Some(suppressed) = LIMITER.check($policy) {
+            $crate::__log_event!($level, { suppressed, }, $($arg)+);
+        }
+    }};
+}
+
+/// Emits an event with the leading `fields` (each followed by a comma), moving
+/// a leading `target:` and/or `parent:` in front of the level where
+/// [`tracing::event!`] expects them.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_event {
+    ($level:expr, { $($field:tt)* }, target: $target:expr, parent: $parent:expr, $($arg:tt)+) => {
+        ::tracing::event!(target: $target, parent: $parent, $level, $($field)* $($arg)+)
+    };
+    ($level:expr, { $($field:tt)* }, target: $target:expr, $($arg:tt)+) => {
+        ::tracing::event!(target: $target, $level, $($field)* $($arg)+)
+    };
+    ($level:expr, { $($field:tt)* }, parent: $parent:expr, $($arg:tt)+) => {
+        ::tracing::event!(parent: $parent, $level, $($field)* $($arg)+)
+    };
+    ($level:expr, { $($field:tt)* }, $($arg:tt)+) => {
+        ::tracing::event!($level, $($field)* $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! error_every {
+    ($interval:expr, $($arg:tt)+) => {
+        $crate::log_every!(::tracing::Level::ERROR, $interval, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! warn_every {
+    ($interval:expr, $($arg:tt)+) => {
+        $crate::log_every!(::tracing::Level::WARN, $interval, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! info_every {
+    ($interval:expr, $($arg:tt)+) => {
+        $crate::log_every!(::tracing::Level::INFO, $interval, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! debug_every {
+    ($interval:expr, $($arg:tt)+) => {
+        $crate::log_every!(::tracing::Level::DEBUG, $interval, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! trace_every {
+    ($interval:expr, $($arg:tt)+) => {
+        $crate::log_every!(::tracing::Level::TRACE, $interval, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! error_every_n {
+    ($n:expr, $($arg:tt)+) => {
+        $crate::log_every_n!(::tracing::Level::ERROR, $n, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! warn_every_n {
+    ($n:expr, $($arg:tt)+) => {
+        $crate::log_every_n!(::tracing::Level::WARN, $n, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! info_every_n {
+    ($n:expr, $($arg:tt)+) => {
+        $crate::log_every_n!(::tracing::Level::INFO, $n, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! debug_every_n {
+    ($n:expr, $($arg:tt)+) => {
+        $crate::log_every_n!(::tracing::Level::DEBUG, $n, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! trace_every_n {
+    ($n:expr, $($arg:tt)+) => {
+        $crate::log_every_n!(::tracing::Level::TRACE, $n, $($arg)+)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tracing::Level;
+
+    use crate::tracing::capture_events;
+
+    #[test]
+    fn every_n_reports_suppressed() {
+        let events = capture_events(|| {
+            for i in 0..7 {
+                info_every_n!(3, i, "Tick {i}");
+            }
+        });
+
+        let events: Vec<_> = events
+            .iter()
+            .map(|event| {
+                assert_eq!(event.level, Level::INFO);
+
+                (event.field("i").unwrap(), event.field("suppressed").unwrap())
+            })
+            .collect();
+        assert_eq!(events, [("0", "0"), ("3", "2"), ("6", "2")]);
+    }
+
+    #[test]
+    fn every_reports_suppressed() {
+        let events = capture_events(|| {
+            for i in 0..3 {
+                if i == 2 {
+                    std::thread::sleep(Duration::from_millis(60));
+                }
+                warn_every!(Duration::from_millis(50), attempt = i, "Retrying");
+            }
+        });
+
+        let events: Vec<_> = events
+            .iter()
+            .map(|event| {
+                assert_eq!(event.level, Level::WARN);
+                assert_eq!(event.message.as_deref(), Some("Retrying"));
+
+                (event.field("attempt").unwrap(), event.field("suppressed").unwrap())
+            })
+            .collect();
+        assert_eq!(events, [("0", "0"), ("2", "1")]);
+    }
+
+    #[test]
+    fn target_and_parent_are_accepted() {
+        let events = capture_events(|| {
+            let span = tracing::info_span!("request");
+            for i in 0..2 {
+                warn_every!(Duration::from_secs(60), target: "net", i, "Reconnecting");
+                info_every_n!(2, parent: &span, i, "Polling");
+                log_once!(Level::DEBUG, target: "net", parent: None, i, "First");
+            }
+        });
+
+        let events: Vec<_> = events
+            .iter()
+            .map(|event| {
+                let suppressed = event.field("suppressed");
+
+                (event.target.as_str(), event.message.as_deref().unwrap(), suppressed)
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("net", "Reconnecting", Some("0")),
+                ("toolbox::tracing::rate_limit::tests", "Polling", Some("0")),
+                ("net", "First", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn once_logs_first_occurrence() {
+        let events = capture_events(|| {
+            for i in 0..3 {
+                log_once!(Level::DEBUG, i, "First");
+            }
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].level, Level::DEBUG);
+        assert_eq!(events[0].field("i"), Some("0"));
+    }
+}
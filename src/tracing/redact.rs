@@ -0,0 +1,512 @@
+use std::fmt;
+use std::sync::Arc;
+
+use regex::Regex;
+use tracing::field::{DebugValue, Field, Value, ValueSet, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Dispatch, Event, Metadata, Subscriber};
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use super::TracingError;
+
+/// The text substituted for redacted values.
+const REDACTED: &str = "[REDACTED]";
+
+/// Masks secrets in event & span fields before they are written by the
+/// stderr, file, journald & syslog outputs.
+///
+/// Spans exported via OTLP are not redacted, the exporter needs direct access
+/// to its layer to propagate span contexts.
+///
+/// Fields whose name matches a field pattern are replaced entirely, field
+/// patterns are case-insensitive & may contain `*` wildcards. Parts of string
+/// & `Debug` values (including messages) matching a value regex are replaced.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use toolbox::tracing::{RedactionConfig, TracingBuilder};
+///
+/// let _handle = TracingBuilder::new("my-crate")
+///     .redaction(
+///         RedactionConfig::default()
+///             .field("*_key")
+///             .field("password")
+///             .value(r"Bearer [A-Za-z0-9._-]+"),
+///     )
+///     .init()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct RedactionConfig {
+    fields: Vec<String>,
+    values: Vec<String>,
+}
+
+impl RedactionConfig {
+    /// Redacts fields whose name matches `pattern`.
+    pub fn field(mut self, pattern: impl Into<String>) -> Self {
+        self.fields.push(pattern.into());
+
+        self
+    }
+
+    /// Redacts the parts of values matching the regex `pattern`.
+    pub fn value(mut self, pattern: impl Into<String>) -> Self {
+        self.values.push(pattern.into());
+
+        self
+    }
+
+    pub(super) fn build(&self) -> Result<Redactor, TracingError> {
+        let values = self
+            .values
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|err| TracingError::Redaction { pattern: pattern.clone(), err })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Redactor(Arc::new(Rules {
+            fields: self
+                .fields
+                .iter()
+                .map(|pattern| pattern.to_ascii_lowercase())
+                .collect(),
+            values,
+        })))
+    }
+}
+
+/// Compiled redaction rules, shared by the outputs.
+#[derive(Debug, Clone)]
+pub(super) struct Redactor(Arc<Rules>);
+
+#[derive(Debug)]
+struct Rules {
+    fields: Vec<String>,
+    values: Vec<Regex>,
+}
+
+impl Redactor {
+    /// Wraps the output layer `inner`, redacting the fields it is given.
+    pub(super) fn layer<L>(&self, inner: L) -> RedactingLayer<L> {
+        RedactingLayer { redactor: self.clone(), inner }
+    }
+
+    /// Records `fields`, returning their redacted values if any were redacted.
+    fn redact<R: RecordFields>(&self, fields: R) -> Option<Recorded<'_>> {
+        let mut recorded = Recorded { redactor: self, fields: Vec::default(), redacted: false };
+        fields.record(&mut recorded);
+
+        recorded.redacted.then_some(recorded)
+    }
+
+    /// Records `fields` into `visitor`, replacing redacted values.
+    #[cfg(feature = "tracing_error")]
+    pub(super) fn record<R: RecordFields>(&self, fields: R, visitor: &mut dyn Visit) {
+        let mut recorded = Recorded { redactor: self, fields: Vec::default(), redacted: false };
+        fields.record(&mut recorded);
+
+        for (field, value) in &recorded.fields {
+            value.as_value().record(field, visitor);
+        }
+    }
+
+    fn redacts_field(&self, name: &str) -> bool {
+        self.0
+            .fields
+            .iter()
+            .any(|pattern| glob_matches(pattern.as_bytes(), name.to_ascii_lowercase().as_bytes()))
+    }
+
+    fn redact_value(&self, value: &str) -> Option<String> {
+        let mut redacted = None;
+        for regex in &self.0.values {
+            let current = redacted.as_deref().unwrap_or(value);
+            if regex.is_match(current) {
+                redacted = Some(regex.replace_all(current, REDACTED).into_owned());
+            }
+        }
+
+        redacted
+    }
+}
+
+/// Redacts event & span fields before passing them to the inner layer.
+///
+/// Redacted fields are passed on as a new event, span or record of the same
+/// callsite, all other calls are forwarded unchanged.
+pub(super) struct RedactingLayer<L> {
+    redactor: Redactor,
+    inner: L,
+}
+
+impl<S, L> Layer<S> for RedactingLayer<L>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    L: Layer<S>,
+{
+    fn on_register_dispatch(&self, subscriber: &Dispatch) {
+        self.inner.on_register_dispatch(subscriber);
+    }
+
+    fn on_layer(&mut self, subscriber: &mut S) {
+        self.inner.on_layer(subscriber);
+    }
+
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        self.inner.register_callsite(metadata)
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, S>) -> bool {
+        self.inner.enabled(metadata, ctx)
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(recorded) = self.redactor.redact(attrs) else {
+            return self.inner.on_new_span(attrs, id, ctx);
+        };
+
+        let metadata = attrs.metadata();
+        recorded.with_values(metadata, |values| {
+            let attrs = match (attrs.parent(), attrs.is_root()) {
+                (Some(parent), _) => Attributes::child_of(parent.clone(), metadata, values),
+                (None, true) => Attributes::new_root(metadata, values),
+                (None, false) => Attributes::new(metadata, values),
+            };
+
+            self.inner.on_new_span(&attrs, id, ctx);
+        });
+    }
+
+    fn on_record(&self, span: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(recorded) = self.redactor.redact(values) else {
+            return self.inner.on_record(span, values, ctx);
+        };
+        // The rebuilt record needs the span's metadata, without it the record is
+        // dropped rather than passed on unredacted.
+        let Some(metadata) = ctx.metadata(span) else {
+            return;
+        };
+
+        recorded.with_values(metadata, |values| {
+            self.inner.on_record(span, &Record::new(values), ctx);
+        });
+    }
+
+    fn on_follows_from(&self, span: &Id, follows: &Id, ctx: Context<'_, S>) {
+        self.inner.on_follows_from(span, follows, ctx);
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, ctx: Context<'_, S>) -> bool {
+        self.inner.event_enabled(event, ctx)
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(recorded) = self.redactor.redact(event) else {
+            return self.inner.on_event(event, ctx);
+        };
+
+        let metadata = event.metadata();
+        recorded.with_values(metadata, |values| {
+            let event = match (event.parent(), event.is_root()) {
+                (Some(parent), _) => Event::new_child_of(parent.clone(), metadata, values),
+                (None, true) => Event::new_child_of(None, metadata, values),
+                (None, false) => Event::new(metadata, values),
+            };
+
+            self.inner.on_event(&event, ctx);
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_enter(id, ctx);
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_exit(id, ctx);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        self.inner.on_close(id, ctx);
+    }
+
+    fn on_id_change(&self, old: &Id, new: &Id, ctx: Context<'_, S>) {
+        self.inner.on_id_change(old, new, ctx);
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        self.inner.max_level_hint()
+    }
+}
+
+/// Field values recorded from an event or span.
+struct Recorded<'a> {
+    redactor: &'a Redactor,
+    fields: Vec<(Field, RecordedValue)>,
+    redacted: bool,
+}
+
+enum RecordedValue {
+    F64(f64),
+    I64(i64),
+    U64(u64),
+    I128(i128),
+    U128(u128),
+    Bool(bool),
+    Str(String),
+    Debug(DebugValue<Verbatim>),
+}
+
+/// Formats the contained string without quoting or escaping.
+struct Verbatim(String);
+
+impl fmt::Debug for Verbatim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl RecordedValue {
+    fn as_value(&self) -> &dyn Value {
+        match self {
+            RecordedValue::F64(value) => value,
+            RecordedValue::I64(value) => value,
+            RecordedValue::U64(value) => value,
+            RecordedValue::I128(value) => value,
+            RecordedValue::U128(value) => value,
+            RecordedValue::Bool(value) => value,
+            RecordedValue::Str(value) => value,
+            RecordedValue::Debug(value) => value,
+        }
+    }
+}
+
+impl Recorded<'_> {
+    fn push(&mut self, field: &Field, value: RecordedValue) {
+        let value = match self.redactor.redacts_field(field.name()) {
+            true => {
+                self.redacted = true;
+
+                RecordedValue::Debug(tracing::field::debug(Verbatim(REDACTED.to_string())))
+            }
+            false => value,
+        };
+
+        self.fields.push((field.clone(), value));
+    }
+
+    fn push_text(&mut self, field: &Field, value: String, debug: bool) {
+        let value = match self.redactor.redact_value(&value) {
+            Some(redacted) => {
+                self.redacted = true;
+
+                redacted
+            }
+            None => value,
+        };
+
+        match debug {
+            true => self.push(field, RecordedValue::Debug(tracing::field::debug(Verbatim(value)))),
+            false => self.push(field, RecordedValue::Str(value)),
+        }
+    }
+
+    /// Calls `f` with the recorded values as a value set of `metadata`'s
+    /// fields.
+    fn with_values<T>(
+        &self,
+        metadata: &'static Metadata<'static>,
+        f: impl FnOnce(&ValueSet<'_>) -> T,
+    ) -> T {
+        // Callsites have at most 32 fields, unused slots are skipped when
+        // recording as they have no value.
+        let mut values: [(&Field, Option<&dyn Value>); 32] = [(&self.fields[0].0, None); 32];
+        for (slot, (field, value)) in values.iter_mut().zip(&self.fields) {
+            *slot = (field, Some(value.as_value()));
+        }
+
+        f(&metadata.fields().value_set(&values))
+    }
+}
+
+impl Visit for Recorded<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, RecordedValue::F64(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, RecordedValue::I64(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, RecordedValue::U64(value));
+    }
+
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.push(field, RecordedValue::I128(value));
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.push(field, RecordedValue::U128(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, RecordedValue::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push_text(field, value.to_string(), false);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push_text(field, format!("{value:?}"), true);
+    }
+}
+
+/// Matches `name` against `pattern`, where `*` matches any sequence.
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| glob_matches(rest, &name[skip..])),
+        Some((char, rest)) => name
+            .split_first()
+            .is_some_and(|(first, name)| first == char && glob_matches(rest, name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::tracing::{LogFormat, SyslogConfig, SyslogTransport, TracingBuilder};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn emit(format: LogFormat) -> String {
+        let redactor = RedactionConfig::default()
+            .field("*_key")
+            .field("password")
+            .value("sk-[a-z0-9]+")
+            .build()
+            .unwrap();
+        let output = Output::default();
+        let writer = output.clone();
+        let layer = tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(move || writer.clone());
+        let layer = match format {
+            LogFormat::Full => redactor.layer(layer).boxed(),
+            LogFormat::Json => redactor.layer(layer.json()).boxed(),
+            _ => unreachable!(),
+        };
+        let subscriber = tracing_subscriber::registry().with(layer);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "request",
+                api_key = "hunter1",
+                password = tracing::field::Empty,
+                user = tracing::field::Empty
+            );
+            span.record("password", "hunter2");
+            span.record("user", "alice");
+            span.in_scope(|| {
+                tracing::info!(
+                    API_KEY = "hunter3",
+                    token = ?"sk-abc123",
+                    attempt = 3,
+                    "Calling with sk-def456"
+                );
+            });
+        });
+
+        let output = output.0.lock().unwrap().clone();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn secrets_never_reach_the_writer() {
+        for format in [LogFormat::Full, LogFormat::Json] {
+            let output = emit(format);
+
+            for secret in ["hunter1", "hunter2", "hunter3", "sk-abc123", "sk-def456"] {
+                assert!(!output.contains(secret), "{format:?}: {output}");
+            }
+            for kept in ["alice", "attempt", "Calling with [REDACTED]", "API_KEY"] {
+                assert!(output.contains(kept), "{format:?}: {output}");
+            }
+        }
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches(b"*_key", b"api_key"));
+        assert!(glob_matches(b"*_key", b"_key"));
+        assert!(glob_matches(b"pass*", b"password"));
+        assert!(glob_matches(b"*secret*", b"my_secret_value"));
+        assert!(!glob_matches(b"*_key", b"api_keys"));
+        assert!(!glob_matches(b"password", b"passwords"));
+    }
+
+    #[test]
+    fn syslog_output_is_redacted() {
+        let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let (layers, _handle) = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .stdout_default_filter("off")
+            .syslog(
+                SyslogConfig::new("toolbox")
+                    .transport(SyslogTransport::Udp(server.local_addr().unwrap())),
+            )
+            .redaction(RedactionConfig::default().field("password"))
+            .layers()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(password = "hunter2", user = "alice", "Logged in");
+        });
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        let message = std::str::from_utf8(&buf[..len]).unwrap();
+        assert!(message.contains("password=\"[REDACTED]\""), "{message}");
+        assert!(message.contains("user=\"alice\""), "{message}");
+    }
+
+    #[test]
+    fn invalid_value_pattern_is_rejected() {
+        let err = RedactionConfig::default().value("(").build().unwrap_err();
+
+        assert!(err
+            .to_string()
+            .starts_with("Invalid redaction pattern; pattern=("));
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+#[cfg(all(unix, feature = "tracing_sighup"))]
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use super::{load_filter, parse_filter, PeriodicReporter, TracingError};
+
+/// Swaps the filters of a running subscriber.
+///
+/// Each output resolves its directives from its env var, then the filter file
+/// (if configured), then its default. As env vars cannot change after startup,
+/// [`ReloadHandle::reload`] is mainly useful together with a filter file.
+///
+/// The filter file contains one `VAR=directives` line per output, keyed by the
+/// output's env var name (e.g. `RUST_LOG=info,my_crate=debug`). Blank lines &
+/// lines starting with `#` are ignored.
+///
+/// With the `tracing_config` feature, files ending in `.toml` or `.json` are
+/// instead read as a config with `stdout` & `file` sections, each holding an
+/// optional `level` & a `modules` table of per-module levels.
+#[derive(Debug, Clone)]
+pub struct ReloadHandle {
+    pub(super) filter_file: Option<PathBuf>,
+    pub(super) stdout: ReloadableFilter,
+    pub(super) file: Option<ReloadableFilter>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct ReloadableFilter {
+    output: &'static str,
+    var: String,
+    default: String,
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl ReloadHandle {
+    /// Replaces the stderr filter with `directives`.
+    pub fn set_stdout_filter(&self, directives: &str) -> Result<(), TracingError> {
+        self.stdout.set(directives)
+    }
+
+    /// Replaces the file filter with `directives`, does nothing if no log
+    /// directory was configured.
+    pub fn set_file_filter(&self, directives: &str) -> Result<(), TracingError> {
+        match &self.file {
+            Some(file) => file.set(directives),
+            None => Ok(()),
+        }
+    }
+
+    /// Re-resolves & applies the directives of all outputs.
+    ///
+    /// No filter is replaced unless all filters are valid.
+    pub fn reload(&self) -> Result<(), TracingError> {
+        let overrides = self.read_filter_file()?;
+        let stdout = self.stdout.load(&overrides)?;
+        let file = self
+            .file
+            .as_ref()
+            .map(|file| file.load(&overrides))
+            .transpose()?;
+
+        self.stdout.handle.reload(stdout)?;
+        if let (Some(reloadable), Some(filter)) = (&self.file, file) {
+            reloadable.handle.reload(filter)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_filter_file(&self) -> Result<HashMap<String, String>, TracingError> {
+        match &self.filter_file {
+            Some(path) => read_filter_file(path),
+            None => Ok(HashMap::default()),
+        }
+    }
+
+    /// Spawns a thread that reloads the filters whenever the filter file's
+    /// modification time changes.
+    pub(super) fn watch_filter_file(
+        &self,
+        poll_interval: Duration,
+    ) -> Result<Option<PeriodicReporter>, TracingError> {
+        let Some(path) = self.filter_file.clone() else {
+            return Ok(None);
+        };
+        let handle = self.clone();
+        let modified = |path: &Path| -> Option<SystemTime> {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+
+        let mut last_modified = modified(&path);
+        let watcher = PeriodicReporter::spawn("tracing-reload", poll_interval, move || {
+            let current = modified(&path);
+            if current != last_modified {
+                last_modified = current;
+                handle.reload_and_log("file_change");
+            }
+        })?;
+
+        Ok(Some(watcher))
+    }
+
+    /// Spawns a thread that reloads the filters whenever `SIGHUP` is received.
+    #[cfg(all(unix, feature = "tracing_sighup"))]
+    pub(super) fn reload_on_sighup(&self) -> Result<SighupReloader, TracingError> {
+        let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])
+            .map_err(TracingError::Signal)?;
+        let signals_handle = signals.handle();
+        let handle = self.clone();
+
+        let thread = std::thread::Builder::new()
+            .name("tracing-sighup".to_string())
+            .spawn(move || {
+                for _ in signals.forever() {
+                    handle.reload_and_log("sighup");
+                }
+            })
+            .map_err(TracingError::Thread)?;
+
+        Ok(SighupReloader { signals: signals_handle, thread: Some(thread) })
+    }
+
+    fn reload_and_log(&self, trigger: &str) {
+        match self.reload() {
+            Ok(()) => tracing::info!(trigger, "Reloaded log filters"),
+            Err(err) => tracing::error!(trigger, %err, "Failed to reload log filters"),
+        }
+    }
+}
+
+/// Reloads the filters whenever `SIGHUP` is received.
+///
+/// Dropping the reloader stops listening for `SIGHUP` & joins its thread.
+#[cfg(all(unix, feature = "tracing_sighup"))]
+#[derive(Debug)]
+pub(super) struct SighupReloader {
+    signals: signal_hook::iterator::Handle,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(all(unix, feature = "tracing_sighup"))]
+impl Drop for SighupReloader {
+    fn drop(&mut self) {
+        self.signals.close();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl ReloadableFilter {
+    /// Loads the initial filter for an output, wrapped in a reload layer.
+    pub(super) fn new(
+        output: &'static str,
+        var: String,
+        default: String,
+        overrides: &HashMap<String, String>,
+    ) -> Result<(reload::Layer<EnvFilter, Registry>, Self), TracingError> {
+        let filter = resolve_filter(output, &var, &default, overrides)?;
+        let (layer, handle) = reload::Layer::new(filter);
+
+        Ok((layer, ReloadableFilter { output, var, default, handle }))
+    }
+
+    fn load(&self, overrides: &HashMap<String, String>) -> Result<EnvFilter, TracingError> {
+        resolve_filter(self.output, &self.var, &self.default, overrides)
+    }
+
+    fn set(&self, directives: &str) -> Result<(), TracingError> {
+        let filter = parse_filter(&self.var, directives)?;
+
+        Ok(self.handle.reload(filter)?)
+    }
+}
+
+/// Resolves an output's filter from its env var, then `overrides` (keyed by env
+/// var or output name), then `default`.
+fn resolve_filter(
+    output: &str,
+    var: &str,
+    default: &str,
+    overrides: &HashMap<String, String>,
+) -> Result<EnvFilter, TracingError> {
+    let directives = overrides
+        .get(var)
+        .or_else(|| overrides.get(output))
+        .map_or(default, String::as_str);
+
+    load_filter(var, directives)
+}
+
+/// Reads the directives from `path`, see [`ReloadHandle`] for the format.
+pub(super) fn read_filter_file(path: &Path) -> Result<HashMap<String, String>, TracingError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| TracingError::FilterFile { path: path.to_path_buf(), err })?;
+
+    #[cfg(feature = "tracing_config")]
+    if let Some(extension @ ("toml" | "json")) = path.extension().and_then(std::ffi::OsStr::to_str)
+    {
+        return super::parse_filter_config(path, extension, &contents);
+    }
+
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(var, directives)| (var.trim().to_string(), directives.trim().to_string()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use tracing::Level;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::tracing::TracingBuilder;
+
+    #[test]
+    fn set_filter_applies_immediately() {
+        let (layers, handle) = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .layers()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(!tracing::enabled!(Level::DEBUG));
+
+            handle.reload_handle().set_stdout_filter("debug").unwrap();
+            assert!(tracing::enabled!(Level::DEBUG));
+
+            assert!(handle.reload_handle().set_stdout_filter("info,[{").is_err());
+            assert!(tracing::enabled!(Level::DEBUG));
+        });
+    }
+
+    #[test]
+    fn reload_reads_filter_file() {
+        let path = std::env::temp_dir().join(format!("toolbox-filters-{}", std::process::id()));
+        std::fs::write(&path, "# Comment\nTOOLBOX_TEST_UNSET_VAR=warn\n").unwrap();
+        let (layers, handle) = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .filter_file(&path)
+            .layers()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(!tracing::enabled!(Level::INFO));
+
+            std::fs::write(&path, "TOOLBOX_TEST_UNSET_VAR = trace\n").unwrap();
+            handle.reload_handle().reload().unwrap();
+            assert!(tracing::enabled!(Level::TRACE));
+        });
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn watcher_reloads_on_change() {
+        let path = std::env::temp_dir().join(format!("toolbox-watched-{}", std::process::id()));
+        std::fs::write(&path, "TOOLBOX_TEST_UNSET_VAR=warn\n").unwrap();
+        let (layers, handle) = TracingBuilder::new("tests")
+            .stdout_env_var("TOOLBOX_TEST_UNSET_VAR")
+            .filter_file(&path)
+            .layers()
+            .unwrap();
+        let subscriber = tracing_subscriber::registry().with(layers);
+        let watcher = handle
+            .reload_handle()
+            .watch_filter_file(Duration::from_secs(3600))
+            .unwrap()
+            .unwrap();
+
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(!tracing::enabled!(Level::INFO));
+
+            std::fs::write(&path, "TOOLBOX_TEST_UNSET_VAR=trace\n").unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(60))
+                .unwrap();
+            // Stopping the watcher checks the file one final time.
+            drop(watcher);
+            assert!(tracing::enabled!(Level::TRACE));
+        });
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use super::TracingError;
+
+/// Runs a report on a background thread every interval.
+///
+/// Dropping the reporter stops the thread after a final report.
+#[derive(Debug)]
+pub(super) struct PeriodicReporter {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PeriodicReporter {
+    pub(super) fn spawn<F>(
+        name: &str,
+        interval: Duration,
+        mut report: F,
+    ) -> Result<Self, TracingError>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let (stop, stopped) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || loop {
+                let result = stopped.recv_timeout(interval);
+                report();
+                if result != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+            })
+            .map_err(TracingError::Thread)?;
+
+        Ok(PeriodicReporter { stop: Some(stop), thread: Some(thread) })
+    }
+}
+
+impl Drop for PeriodicReporter {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use time::{Duration, OffsetDateTime, Time};
+use tracing_appender::rolling::Rotation;
+
+/// A log file writer with time & size based rotation plus retention.
+///
+/// Files are named `{prefix}.{date}` (matching `tracing_appender`), with a
+/// `.{n}` suffix appended whenever a file is split due to
+/// [`RollingOptions::max_file_size`]. Whenever a new file is started the oldest
+/// files are deleted until both [`RollingOptions::max_files`] &
+/// [`RollingOptions::max_total_size`] are satisfied. The active file is never
+/// deleted.
+///
+/// Rotation & cleanup happen inline on the writing thread, wrap the writer in
+/// [`tracing_appender::non_blocking()`] to keep them off the hot path.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use tracing_appender::rolling::Rotation;
+/// use toolbox::tracing::{RollingFile, RollingOptions};
+///
+/// let writer = RollingFile::new(
+///     "/var/log/my-crate",
+///     "my-crate.log",
+///     RollingOptions {
+///         rotation: Rotation::DAILY,
+///         max_file_size: Some(64 * 1024 * 1024),
+///         max_total_size: Some(1024 * 1024 * 1024),
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// let (writer, _guard) = tracing_appender::non_blocking(writer);
+/// ```
+#[derive(Debug)]
+pub struct RollingFile {
+    directory: LogDirectory,
+    period: String,
+    next_rotation: Option<OffsetDateTime>,
+    active: ActiveFile,
+}
+
+/// Rotation & retention settings for a [`RollingFile`].
+#[derive(Debug, Clone)]
+pub struct RollingOptions {
+    /// How often a new file is started, defaults to [`Rotation::HOURLY`].
+    pub rotation: Rotation,
+    /// Starts a new file once a write would grow the current file beyond this
+    /// many bytes, defaults to unlimited.
+    pub max_file_size: Option<u64>,
+    /// Maximum number of files to retain (including the active file), defaults
+    /// to unlimited.
+    pub max_files: Option<usize>,
+    /// Maximum combined size in bytes of all retained files, defaults to
+    /// unlimited.
+    pub max_total_size: Option<u64>,
+    /// Whether to gzip files once they are rotated out, defaults to `false`.
+    #[cfg(feature = "tracing_gzip")]
+    pub compress: bool,
+}
+
+impl Default for RollingOptions {
+    fn default() -> Self {
+        RollingOptions {
+            rotation: Rotation::HOURLY,
+            max_file_size: None,
+            max_files: None,
+            max_total_size: None,
+            #[cfg(feature = "tracing_gzip")]
+            compress: false,
+        }
+    }
+}
+
+impl RollingFile {
+    /// Opens (or resumes) the current log file in `directory`, creating the
+    /// directory if required.
+    pub fn new(
+        directory: impl Into<PathBuf>,
+        prefix: impl Into<String>,
+        options: RollingOptions,
+    ) -> io::Result<Self> {
+        let directory = LogDirectory { path: directory.into(), prefix: prefix.into(), options };
+        fs::create_dir_all(&directory.path)?;
+
+        let (period, next_rotation) =
+            period(&directory.options.rotation, OffsetDateTime::now_utc());
+        let active = directory.resume(&period)?;
+        directory.prune(&active);
+
+        Ok(RollingFile { directory, period, next_rotation, active })
+    }
+
+    fn write_at(&mut self, buf: &[u8], now: OffsetDateTime) -> io::Result<()> {
+        if self.next_rotation.is_some_and(|next| now >= next) {
+            (self.period, self.next_rotation) = period(&self.directory.options.rotation, now);
+            let next = self.directory.resume(&self.period)?;
+            self.roll(next);
+        } else if self
+            .directory
+            .options
+            .max_file_size
+            .is_some_and(|max| self.active.size > 0 && self.active.size + buf.len() as u64 > max)
+        {
+            let next = self.directory.open(&self.period, self.active.index + 1)?;
+            self.roll(next);
+        }
+
+        self.active.file.write_all(buf)?;
+        self.active.size += buf.len() as u64;
+
+        Ok(())
+    }
+
+    fn roll(&mut self, next: ActiveFile) {
+        let previous = std::mem::replace(&mut self.active, next);
+        drop(previous.file);
+
+        #[cfg(feature = "tracing_gzip")]
+        if self.directory.options.compress {
+            if let Err(err) = compress(&previous.path) {
+                eprintln!(
+                    "Failed to compress log file; path={}; err={err}",
+                    previous.path.display()
+                );
+            }
+        }
+
+        self.directory.prune(&self.active);
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_at(buf, OffsetDateTime::now_utc())?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.active.file.flush()
+    }
+}
+
+#[derive(Debug)]
+struct LogDirectory {
+    path: PathBuf,
+    prefix: String,
+    options: RollingOptions,
+}
+
+#[derive(Debug)]
+struct ActiveFile {
+    path: PathBuf,
+    index: u64,
+    file: File,
+    size: u64,
+}
+
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    period: String,
+    index: u64,
+    compressed: bool,
+    size: u64,
+}
+
+impl LogDirectory {
+    /// Opens the newest file for `period`, moving on to the next index if that
+    /// file is already full or compressed.
+    fn resume(&self, period: &str) -> io::Result<ActiveFile> {
+        let index = self
+            .log_files()?
+            .into_iter()
+            .filter(|file| file.period == period)
+            .max_by_key(|file| file.index)
+            .map_or(0, |latest| match latest.compressed || self.is_full(latest.size) {
+                true => latest.index + 1,
+                false => latest.index,
+            });
+
+        self.open(period, index)
+    }
+
+    /// Opens the file for `period` & `index` in append mode.
+    fn open(&self, period: &str, index: u64) -> io::Result<ActiveFile> {
+        let mut name = self.prefix.clone();
+        if !period.is_empty() {
+            name = format!("{name}.{period}");
+        }
+        if index > 0 {
+            name = format!("{name}.{index}");
+        }
+        let path = self.path.join(name);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(ActiveFile { path, index, file, size })
+    }
+
+    fn is_full(&self, size: u64) -> bool {
+        self.options.max_file_size.is_some_and(|max| size >= max)
+    }
+
+    /// Deletes the oldest files until the retention limits are satisfied.
+    fn prune(&self, active: &ActiveFile) {
+        let files = match self.log_files() {
+            Ok(files) => files,
+            Err(err) => {
+                eprintln!("Failed to read log directory; path={}; err={err}", self.path.display());
+
+                return;
+            }
+        };
+
+        let mut count = 1;
+        let mut total_size = active.size;
+        let mut exceeded = false;
+        for file in files.iter().rev().filter(|file| file.path != active.path) {
+            count += 1;
+            total_size += file.size;
+            exceeded = exceeded
+                || self.options.max_files.is_some_and(|max| count > max)
+                || self
+                    .options
+                    .max_total_size
+                    .is_some_and(|max| total_size > max);
+
+            if exceeded {
+                if let Err(err) = fs::remove_file(&file.path) {
+                    eprintln!("Failed to remove log file; path={}; err={err}", file.path.display());
+                }
+            }
+        }
+    }
+
+    /// All log files belonging to this writer, oldest first.
+    fn log_files(&self) -> io::Result<Vec<LogFile>> {
+        let mut files = Vec::default();
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let Some((period, index, compressed)) = self.parse_name(&name) else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+
+            files.push(LogFile {
+                path: entry.path(),
+                period: period.to_string(),
+                index,
+                compressed,
+                size: metadata.len(),
+            });
+        }
+        files.sort_by(|a, b| (&a.period, a.index).cmp(&(&b.period, b.index)));
+
+        Ok(files)
+    }
+
+    /// Parses `{prefix}[.{period}][.{index}][.gz]` into its components.
+    fn parse_name<'a>(&self, name: &'a str) -> Option<(&'a str, u64, bool)> {
+        let rest = name.strip_prefix(&self.prefix)?;
+        let (rest, compressed) = match rest.strip_suffix(".gz") {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        let rest = match rest.is_empty() {
+            true => rest,
+            false => rest.strip_prefix('.')?,
+        };
+
+        let (period, index) = match self.options.rotation == Rotation::NEVER {
+            true => ("", rest),
+            false => rest.split_once('.').unwrap_or((rest, "")),
+        };
+        if self.options.rotation != Rotation::NEVER
+            && (period.is_empty()
+                || !period
+                    .chars()
+                    .all(|char| char.is_ascii_digit() || char == '-'))
+        {
+            return None;
+        }
+        let index = match index.is_empty() {
+            true => 0,
+            false => index.parse().ok()?,
+        };
+
+        Some((period, index, compressed))
+    }
+}
+
+/// The period label for `now` & the instant the next period starts.
+fn period(rotation: &Rotation, now: OffsetDateTime) -> (String, Option<OffsetDateTime>) {
+    let midnight = now.replace_time(Time::MIDNIGHT);
+    let date = format!("{:04}-{:02}-{:02}", now.year(), u8::from(now.month()), now.day());
+    let hour = Duration::hours(i64::from(now.hour()));
+    let minute = Duration::minutes(i64::from(now.minute()));
+
+    if *rotation == Rotation::MINUTELY {
+        let label = format!("{date}-{:02}-{:02}", now.hour(), now.minute());
+
+        (label, Some(midnight + hour + minute + Duration::MINUTE))
+    } else if *rotation == Rotation::HOURLY {
+        (format!("{date}-{:02}", now.hour()), Some(midnight + hour + Duration::HOUR))
+    } else if *rotation == Rotation::DAILY {
+        (date, Some(midnight + Duration::DAY))
+    } else {
+        (String::new(), None)
+    }
+}
+
+/// Compresses `path` to `{path}.gz` & removes the original.
+#[cfg(feature = "tracing_gzip")]
+fn compress(path: &std::path::Path) -> io::Result<()> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let mut compressed = path.as_os_str().to_owned();
+    compressed.push(".gz");
+
+    let mut input = File::open(path)?;
+    let mut encoder = GzEncoder::new(File::create(compressed)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn test_directory(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("toolbox-rolling-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+
+        path
+    }
+
+    fn file_names(path: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+
+        names
+    }
+
+    #[test]
+    fn size_rotation_retains_newest_files() {
+        let path = test_directory("size");
+        let options = RollingOptions {
+            rotation: Rotation::NEVER,
+            max_file_size: Some(10),
+            max_files: Some(3),
+            ..Default::default()
+        };
+        let mut writer = RollingFile::new(&path, "app.log", options.clone()).unwrap();
+
+        for i in 0..5 {
+            writer.write_all(format!("line {i}!\n").as_bytes()).unwrap();
+        }
+        assert_eq!(file_names(&path), ["app.log.2", "app.log.3", "app.log.4"]);
+        assert_eq!(fs::read_to_string(path.join("app.log.4")).unwrap(), "line 4!\n");
+
+        // A fresh writer resumes the newest file while it has space.
+        drop(writer);
+        let mut writer = RollingFile::new(&path, "app.log", options).unwrap();
+        writer.write_all(b"x").unwrap();
+        assert_eq!(fs::read_to_string(path.join("app.log.4")).unwrap(), "line 4!\nx");
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn time_rotation_retains_total_size() {
+        let path = test_directory("time");
+        let options = RollingOptions { max_total_size: Some(10), ..Default::default() };
+        let mut writer = RollingFile::new(&path, "app.log", options).unwrap();
+
+        // 2030-01-01 00:30 UTC.
+        let start = OffsetDateTime::from_unix_timestamp(1_893_457_800).unwrap();
+        for hour in 0..4 {
+            writer
+                .write_at(b"1234567\n", start + Duration::hours(hour))
+                .unwrap();
+        }
+
+        // Limits are enforced as each file starts, so the newest file may grow
+        // the total beyond the limit.
+        assert_eq!(file_names(&path), ["app.log.2030-01-01-02", "app.log.2030-01-01-03"]);
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "tracing_gzip")]
+    fn rotated_files_are_compressed() {
+        use std::io::Read;
+
+        use flate2::read::GzDecoder;
+
+        let path = test_directory("gzip");
+        let options = RollingOptions {
+            rotation: Rotation::NEVER,
+            max_file_size: Some(10),
+            compress: true,
+            ..Default::default()
+        };
+        let mut writer = RollingFile::new(&path, "app.log", options).unwrap();
+
+        writer.write_all(b"line 0!\n").unwrap();
+        writer.write_all(b"line 1!\n").unwrap();
+        assert_eq!(file_names(&path), ["app.log.1", "app.log.gz"]);
+
+        writer.write_all(b"line 2!\n").unwrap();
+        assert_eq!(file_names(&path), ["app.log.1.gz", "app.log.2", "app.log.gz"]);
+
+        let mut content = String::new();
+        GzDecoder::new(File::open(path.join("app.log.1.gz")).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "line 1!\n");
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
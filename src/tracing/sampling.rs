@@ -0,0 +1,227 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+use super::BoxedLayer;
+
+/// Bounds the volume of events from noisy targets by sampling them.
+///
+/// Each rule matches a target (and its sub-modules) at a level & everything
+/// more verbose. Events are checked against the rules in the order they were
+/// added & the first match decides whether the event is kept, unmatched events
+/// are always kept. Sampling applies to all outputs & each rule's budget is
+/// shared by all events it matches.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use toolbox::tracing::{SampleRate, SamplingConfig, TracingBuilder};
+/// use tracing::Level;
+///
+/// let _handle = TracingBuilder::new("my-crate")
+///     .sampling(
+///         SamplingConfig::default()
+///             .rule("my_crate::hot_path", Level::DEBUG, SampleRate::OneIn(100))
+///             .rule("hyper", Level::TRACE, SampleRate::PerSecond(10)),
+///     )
+///     .init()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct SamplingConfig {
+    rules: Vec<(String, Level, SampleRate)>,
+}
+
+/// How many of the events matched by a sampling rule are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleRate {
+    /// Keep the 1st, (N+1)th, (2N+1)th, ... event.
+    OneIn(u64),
+    /// Keep at most N events per second (using a token bucket allowing bursts
+    /// of up to N events).
+    PerSecond(u32),
+}
+
+impl SamplingConfig {
+    /// Samples events from `target` at `level` (or more verbose) at `rate`.
+    pub fn rule(mut self, target: impl Into<String>, level: Level, rate: SampleRate) -> Self {
+        self.rules.push((target.into(), level, rate));
+
+        self
+    }
+
+    pub(super) fn layer(&self) -> BoxedLayer {
+        SamplingLayer {
+            rules: self
+                .rules
+                .iter()
+                .map(|(target, level, rate)| Rule {
+                    target: target.clone(),
+                    level: *level,
+                    sampler: Sampler::new(*rate),
+                })
+                .collect(),
+        }
+        .boxed()
+    }
+}
+
+struct SamplingLayer {
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    target: String,
+    level: Level,
+    sampler: Sampler,
+}
+
+impl Rule {
+    fn matches(&self, target: &str, level: &Level) -> bool {
+        let target_matches = target
+            .strip_prefix(self.target.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"));
+
+        target_matches && *level >= self.level
+    }
+}
+
+enum Sampler {
+    OneIn { n: u64, count: AtomicU64 },
+    PerSecond { rate: f64, bucket: Mutex<Bucket> },
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Sampler {
+    fn new(rate: SampleRate) -> Self {
+        match rate {
+            SampleRate::OneIn(n) => Sampler::OneIn { n: n.max(1), count: AtomicU64::new(0) },
+            SampleRate::PerSecond(rate) => Sampler::PerSecond {
+                rate: f64::from(rate),
+                bucket: Mutex::new(Bucket { tokens: f64::from(rate), refilled: Instant::now() }),
+            },
+        }
+    }
+
+    fn keep(&self) -> bool {
+        match self {
+            Sampler::OneIn { n, count } => count.fetch_add(1, Ordering::Relaxed) % n == 0,
+            Sampler::PerSecond { rate, bucket } => {
+                let mut bucket = bucket.lock().unwrap_or_else(PoisonError::into_inner);
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * rate).min(*rate);
+                bucket.refilled = now;
+
+                match bucket.tokens >= 1.0 {
+                    true => {
+                        bucket.tokens -= 1.0;
+
+                        true
+                    }
+                    false => false,
+                }
+            }
+        }
+    }
+}
+
+impl<S> Layer<S> for SamplingLayer
+where
+    S: Subscriber,
+{
+    fn event_enabled(&self, event: &Event<'_>, _: Context<'_, S>) -> bool {
+        let metadata = event.metadata();
+
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(metadata.target(), metadata.level()))
+            .is_none_or(|rule| rule.sampler.keep())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::tracing::CaptureLayer;
+
+    fn sample(config: &SamplingConfig, f: impl FnOnce()) -> Vec<(Level, String)> {
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(config.layer())
+            .with(capture.clone());
+        tracing::subscriber::with_default(subscriber, f);
+
+        capture
+            .events()
+            .into_iter()
+            .map(|event| (event.level, event.message.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn one_in_n_keeps_every_nth_event() {
+        let config =
+            SamplingConfig::default().rule(module_path!(), Level::DEBUG, SampleRate::OneIn(3));
+
+        let events = sample(&config, || {
+            for i in 0..7 {
+                tracing::trace!("{i}");
+                tracing::info!("{i}");
+            }
+        });
+
+        let expected: Vec<_> = (0..7)
+            .flat_map(|i| {
+                [
+                    (i % 3 == 0).then(|| (Level::TRACE, i.to_string())),
+                    Some((Level::INFO, i.to_string())),
+                ]
+            })
+            .flatten()
+            .collect();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn per_second_bounds_bursts() {
+        let config = SamplingConfig::default()
+            .rule("toolbox::tracing::sampling::tests::other", Level::TRACE, SampleRate::OneIn(1000))
+            .rule("toolbox::tracing", Level::WARN, SampleRate::PerSecond(2))
+            .rule(module_path!(), Level::ERROR, SampleRate::OneIn(1000));
+
+        let events = sample(&config, || {
+            for i in 0..5 {
+                tracing::warn!("{i}");
+            }
+        });
+
+        assert_eq!(events, [(Level::WARN, "0".to_string()), (Level::WARN, "1".to_string())]);
+    }
+
+    #[test]
+    fn rules_match_module_boundaries() {
+        let rule = Rule {
+            target: "my_crate::db".to_string(),
+            level: Level::DEBUG,
+            sampler: Sampler::new(SampleRate::OneIn(1)),
+        };
+
+        assert!(rule.matches("my_crate::db", &Level::DEBUG));
+        assert!(rule.matches("my_crate::db::pool", &Level::TRACE));
+        assert!(!rule.matches("my_crate::db", &Level::INFO));
+        assert!(!rule.matches("my_crate::dbx", &Level::DEBUG));
+        assert!(!rule.matches("my_crate", &Level::DEBUG));
+    }
+}
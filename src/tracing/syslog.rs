@@ -0,0 +1,359 @@
+use std::fmt::Write as _;
+use std::net::{SocketAddr, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use time::OffsetDateTime;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use super::capture::FieldVisitor;
+use super::{parse_filter, BoxedLayer, TracingError};
+
+/// Sends events as RFC 5424 syslog messages.
+///
+/// Event fields (including the target) are appended to the message as
+/// `name="value"` pairs, or sent as structured data if an SD-ID is configured
+/// (see [`SyslogConfig::structured_data_id`]).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use toolbox::tracing::{SyslogConfig, SyslogTransport, TracingBuilder};
+///
+/// let _handle = TracingBuilder::new("my-crate")
+///     .syslog(
+///         SyslogConfig::new("my-crate")
+///             .transport(SyslogTransport::Udp("10.0.0.1:514".parse().unwrap())),
+///     )
+///     .init()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SyslogConfig {
+    app_name: String,
+    transport: SyslogTransport,
+    hostname: Option<String>,
+    facility: u8,
+    structured_data_id: Option<String>,
+    filter: String,
+}
+
+/// Where syslog messages are sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyslogTransport {
+    /// A local datagram socket, typically `/dev/log`.
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// A remote syslog server.
+    Udp(SocketAddr),
+}
+
+impl SyslogConfig {
+    /// Creates a config sending to `/dev/log` (or `127.0.0.1:514` on non-unix
+    /// platforms) as `app_name`.
+    pub fn new(app_name: impl Into<String>) -> Self {
+        SyslogConfig {
+            app_name: app_name.into(),
+            #[cfg(unix)]
+            transport: SyslogTransport::Unix(PathBuf::from("/dev/log")),
+            #[cfg(not(unix))]
+            transport: SyslogTransport::Udp(SocketAddr::from(([127, 0, 0, 1], 514))),
+            hostname: None,
+            facility: 1,
+            structured_data_id: None,
+            filter: "info".to_string(),
+        }
+    }
+
+    /// Where messages are sent, see [`SyslogConfig::new`] for the default.
+    pub fn transport(mut self, transport: SyslogTransport) -> Self {
+        self.transport = transport;
+
+        self
+    }
+
+    /// The hostname sent with each message, defaults to the system hostname
+    /// (if it can be determined).
+    pub fn hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = Some(hostname.into());
+
+        self
+    }
+
+    /// The syslog facility code (0-23), defaults to `1` (user).
+    pub fn facility(mut self, facility: u8) -> Self {
+        self.facility = facility.min(23);
+
+        self
+    }
+
+    /// Sends event fields as structured data under `id`, defaults to none.
+    ///
+    /// The SD-ID should be `name@<private enterprise number>`, using the number
+    /// IANA assigned to your organisation.
+    pub fn structured_data_id(mut self, id: impl Into<String>) -> Self {
+        self.structured_data_id = Some(id.into());
+
+        self
+    }
+
+    /// Filter directives for this output, defaults to `info`.
+    pub fn filter(mut self, directives: impl Into<String>) -> Self {
+        self.filter = directives.into();
+
+        self
+    }
+
+    pub(super) fn layer(&self) -> Result<BoxedLayer, TracingError> {
+        let filter = parse_filter("syslog", &self.filter)?;
+        let socket = match &self.transport {
+            #[cfg(unix)]
+            SyslogTransport::Unix(path) => {
+                let socket = UnixDatagram::unbound().map_err(TracingError::Output)?;
+                socket.connect(path).map_err(TracingError::Output)?;
+
+                Socket::Unix(socket)
+            }
+            SyslogTransport::Udp(addr) => {
+                let local: SocketAddr = match addr {
+                    SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+                    SocketAddr::V6(_) => ([0; 16], 0).into(),
+                };
+                let socket = UdpSocket::bind(local).map_err(TracingError::Output)?;
+                socket.connect(addr).map_err(TracingError::Output)?;
+
+                Socket::Udp(socket)
+            }
+        };
+        let hostname = self
+            .hostname
+            .clone()
+            .or_else(|| {
+                std::fs::read_to_string("/proc/sys/kernel/hostname")
+                    .ok()
+                    .map(|hostname| hostname.trim().to_string())
+            })
+            .unwrap_or_default();
+
+        Ok(SyslogLayer {
+            socket,
+            header: format!(
+                "{} {} {}",
+                header_field(&hostname, 255),
+                header_field(&self.app_name, 48),
+                std::process::id()
+            ),
+            facility: self.facility,
+            structured_data_id: self.structured_data_id.as_deref().map(param_name),
+        }
+        .with_filter(filter)
+        .boxed())
+    }
+}
+
+#[derive(Debug)]
+enum Socket {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+}
+
+struct SyslogLayer {
+    socket: Socket,
+    /// The `HOSTNAME APP-NAME PROCID` part of the header.
+    header: String,
+    facility: u8,
+    structured_data_id: Option<String>,
+}
+
+impl<S> Layer<S> for SyslogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+
+        // `]` only needs escaping within structured data.
+        let structured = self.structured_data_id.is_some();
+        let escape = |value: &str| escape_param(value, structured);
+        let mut params = format!("target=\"{}\"", escape(event.metadata().target()));
+        for (name, value) in &visitor.fields {
+            let _ = write!(params, " {}=\"{}\"", param_name(name), escape(value));
+        }
+
+        let priority = self.facility * 8 + severity(event.metadata().level());
+        let mut message =
+            format!("<{priority}>1 {} {} - ", timestamp(OffsetDateTime::now_utc()), self.header);
+        match &self.structured_data_id {
+            Some(id) => {
+                let _ = write!(message, "[{id} {params}]");
+                if let Some(text) = visitor.message {
+                    message.push(' ');
+                    message.push_str(&text);
+                }
+            }
+            None => {
+                message.push_str("- ");
+                if let Some(text) = visitor.message {
+                    message.push_str(&text);
+                    message.push(' ');
+                }
+                message.push_str(&params);
+            }
+        }
+
+        // Delivery is best effort, there is nowhere to report failures to.
+        let _ = match &self.socket {
+            #[cfg(unix)]
+            Socket::Unix(socket) => socket.send(message.as_bytes()),
+            Socket::Udp(socket) => socket.send(message.as_bytes()),
+        };
+    }
+}
+
+/// The syslog severity for `level`.
+pub(super) fn severity(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        Level::DEBUG | Level::TRACE => 7,
+    }
+}
+
+/// Formats `now` as an RFC 3339 timestamp with microsecond precision.
+fn timestamp(now: OffsetDateTime) -> String {
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        now.microsecond()
+    )
+}
+
+/// Restricts `value` to the characters & length allowed in a header field,
+/// using the NILVALUE if it's empty.
+fn header_field(value: &str, max_len: usize) -> String {
+    match value.is_empty() {
+        true => "-".to_string(),
+        false => value
+            .chars()
+            .map(|char| match char.is_ascii_graphic() {
+                true => char,
+                false => '_',
+            })
+            .take(max_len)
+            .collect(),
+    }
+}
+
+/// Restricts `name` to the characters & length allowed in an SD-NAME.
+fn param_name(name: &str) -> String {
+    name.chars()
+        .map(|char| match char {
+            '=' | ' ' | ']' | '"' => '_',
+            char if char.is_ascii_graphic() => char,
+            _ => '_',
+        })
+        .take(32)
+        .collect()
+}
+
+/// Escapes the characters RFC 5424 requires escaping in a PARAM-VALUE, leaving
+/// `]` as is outside of `structured` data.
+fn escape_param(value: &str, structured: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for char in value.chars() {
+        if matches!(char, '"' | '\\') || (structured && char == ']') {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    fn emit(config: &SyslogConfig) {
+        let subscriber = tracing_subscriber::registry().with(config.layer().unwrap());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(peer = "a\"b]", attempt = 3, "Connection lost");
+        });
+    }
+
+    fn assert_message(message: &str, structured_data: bool) {
+        let (header, rest) = message.split_once(" - ").unwrap();
+        assert!(header.starts_with("<12>1 "));
+        assert!(header.ends_with(&format!(" test-host toolbox {}", std::process::id())));
+        let params = "target=\"toolbox::tracing::syslog::tests\" attempt=\"3\"";
+        match structured_data {
+            true => assert_eq!(
+                rest,
+                format!("[fields@32473 {params} peer=\"a\\\"b\\]\"] Connection lost")
+            ),
+            false => assert_eq!(rest, format!("- Connection lost {params} peer=\"a\\\"b]\"")),
+        }
+    }
+
+    #[test]
+    fn sends_rfc5424_over_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = SyslogConfig::new("toolbox")
+            .hostname("test-host")
+            .facility(1)
+            .transport(SyslogTransport::Udp(server.local_addr().unwrap()));
+
+        emit(&config);
+        emit(&config.structured_data_id("fields@32473"));
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        assert_message(std::str::from_utf8(&buf[..len]).unwrap(), false);
+        let len = server.recv(&mut buf).unwrap();
+        assert_message(std::str::from_utf8(&buf[..len]).unwrap(), true);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sends_rfc5424_over_unix_socket() {
+        let path = std::env::temp_dir().join(format!("toolbox-syslog-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = UnixDatagram::bind(&path).unwrap();
+        let config = SyslogConfig::new("toolbox")
+            .hostname("test-host")
+            .structured_data_id("fields@32473")
+            .transport(SyslogTransport::Unix(path.clone()));
+
+        emit(&config);
+
+        let mut buf = [0; 1024];
+        let len = server.recv(&mut buf).unwrap();
+        assert_message(std::str::from_utf8(&buf[..len]).unwrap(), true);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn header_fields_are_sanitised() {
+        assert_eq!(header_field("my app\u{e9}", 48), "my_app_");
+        assert_eq!(header_field(&"a".repeat(60), 48), "a".repeat(48));
+        assert_eq!(header_field("", 255), "-");
+    }
+}
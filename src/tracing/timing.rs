@@ -0,0 +1,374 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use super::{parse_filter, BoxedLayer, PeriodicReporter, TracingError};
+
+/// Measures how long spans take, warning about slow spans & periodically
+/// logging a per-name summary.
+///
+/// A span's duration is the time from its creation until it closes, split into
+/// busy (entered) & idle time. Spans exceeding their threshold emit a `Slow
+/// span` warning when closed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use toolbox::tracing::{SpanTimingConfig, TracingBuilder};
+///
+/// let _handle = TracingBuilder::new("my-crate")
+///     .span_timing(
+///         SpanTimingConfig::default()
+///             .default_threshold(Duration::from_secs(1))
+///             .threshold("db_query", Duration::from_millis(100)),
+///     )
+///     .init()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct SpanTimingConfig {
+    thresholds: HashMap<String, Duration>,
+    default_threshold: Option<Duration>,
+    summary_interval: Option<Duration>,
+    filter: String,
+}
+
+impl Default for SpanTimingConfig {
+    fn default() -> Self {
+        SpanTimingConfig {
+            thresholds: HashMap::default(),
+            default_threshold: None,
+            summary_interval: Some(Duration::from_secs(60)),
+            filter: "info".to_string(),
+        }
+    }
+}
+
+impl SpanTimingConfig {
+    /// Warn about spans named `name` that take longer than `threshold`.
+    pub fn threshold(mut self, name: impl Into<String>, threshold: Duration) -> Self {
+        self.thresholds.insert(name.into(), threshold);
+
+        self
+    }
+
+    /// Threshold for spans without a per-name threshold, defaults to none.
+    pub fn default_threshold(mut self, threshold: Duration) -> Self {
+        self.default_threshold = Some(threshold);
+
+        self
+    }
+
+    /// How often the per-name summary is logged, defaults to every minute.
+    ///
+    /// `None` disables the summary.
+    pub fn summary_interval(mut self, interval: Option<Duration>) -> Self {
+        self.summary_interval = interval;
+
+        self
+    }
+
+    /// Filter directives selecting the timed spans, defaults to `info`.
+    pub fn filter(mut self, directives: impl Into<String>) -> Self {
+        self.filter = directives.into();
+
+        self
+    }
+
+    pub(super) fn layer(&self) -> Result<(BoxedLayer, Option<PeriodicReporter>), TracingError> {
+        let filter = parse_filter("span_timing", &self.filter)?;
+        // Durations are only recorded if there's a reporter to summarise them.
+        let samples = self.summary_interval.map(|_| Samples::default());
+        let reporter = self
+            .summary_interval
+            .zip(samples.clone())
+            .map(|(interval, samples)| {
+                PeriodicReporter::spawn("tracing-span-timing", interval, move || {
+                    report_summary(&samples);
+                })
+            })
+            .transpose()?;
+        let layer = SpanTimingLayer {
+            thresholds: self.thresholds.clone(),
+            default_threshold: self.default_threshold,
+            samples,
+        };
+
+        Ok((layer.with_filter(filter).boxed(), reporter))
+    }
+}
+
+/// Span durations recorded since the last summary, keyed by span name.
+type Samples = Arc<Mutex<BTreeMap<&'static str, Histogram>>>;
+
+struct SpanTimingLayer {
+    thresholds: HashMap<String, Duration>,
+    default_threshold: Option<Duration>,
+    samples: Option<Samples>,
+}
+
+/// A log-linear histogram of durations.
+///
+/// Each power of two is split into 16 buckets, bounding the memory used to
+/// under 1000 counters & the error of the reported percentiles to ~6%.
+#[derive(Debug, Default)]
+struct Histogram {
+    count: u64,
+    buckets: Vec<u64>,
+}
+
+impl Histogram {
+    fn record(&mut self, duration: Duration) {
+        let index = Histogram::index(u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX));
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+
+        self.buckets[index] += 1;
+        self.count += 1;
+    }
+
+    /// The nearest-rank `percentile`, as the midpoint of its bucket.
+    fn percentile(&self, percentile: u64) -> Duration {
+        let rank = (self.count * percentile).div_ceil(100).max(1);
+
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Histogram::midpoint(index);
+            }
+        }
+
+        Duration::ZERO
+    }
+
+    fn index(nanos: u64) -> usize {
+        let index = match nanos < 16 {
+            true => nanos,
+            false => {
+                // The 4 bits below the highest set bit select the bucket.
+                let exponent = u64::from(63 - nanos.leading_zeros());
+
+                (exponent - 3) * 16 + ((nanos >> (exponent - 4)) & 15)
+            }
+        };
+
+        usize::try_from(index).unwrap_or(usize::MAX)
+    }
+
+    fn midpoint(index: usize) -> Duration {
+        let index = index as u64;
+        if index < 16 {
+            return Duration::from_nanos(index);
+        }
+
+        let shift = index / 16 - 1;
+        let lower = (16 + index % 16) << shift;
+
+        Duration::from_nanos(lower + (1 << shift) / 2)
+    }
+}
+
+/// Timing state stored in each span's extensions.
+struct Timing {
+    busy: Duration,
+    idle: Duration,
+    last: Instant,
+    entered: usize,
+}
+
+impl<S> Layer<S> for SpanTimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Timing {
+                busy: Duration::ZERO,
+                idle: Duration::ZERO,
+                last: Instant::now(),
+                entered: 0,
+            });
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(timing) = extensions.get_mut::<Timing>() else {
+            return;
+        };
+
+        if timing.entered == 0 {
+            let now = Instant::now();
+            timing.idle += now - timing.last;
+            timing.last = now;
+        }
+        timing.entered += 1;
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(timing) = extensions.get_mut::<Timing>() else {
+            return;
+        };
+
+        timing.entered = timing.entered.saturating_sub(1);
+        if timing.entered == 0 {
+            let now = Instant::now();
+            timing.busy += now - timing.last;
+            timing.last = now;
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(mut timing) = span.extensions_mut().remove::<Timing>() else {
+            return;
+        };
+        timing.idle += timing.last.elapsed();
+
+        let name = span.name();
+        let duration = timing.busy + timing.idle;
+        if let Some(samples) = &self.samples {
+            samples
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(name)
+                .or_default()
+                .record(duration);
+        }
+
+        let threshold = self
+            .thresholds
+            .get(name)
+            .copied()
+            .or(self.default_threshold);
+        if let Some(threshold) = threshold.filter(|threshold| duration > *threshold) {
+            tracing::warn!(
+                span = name,
+                ?duration,
+                busy = ?timing.busy,
+                idle = ?timing.idle,
+                ?threshold,
+                "Slow span"
+            );
+        }
+    }
+}
+
+/// Logs the count & percentiles of each span name, then clears the samples.
+fn report_summary(samples: &Samples) {
+    let samples = std::mem::take(&mut *samples.lock().unwrap_or_else(PoisonError::into_inner));
+
+    for (name, histogram) in samples {
+        tracing::info!(
+            span = name,
+            count = histogram.count,
+            p50 = ?histogram.percentile(50),
+            p99 = ?histogram.percentile(99),
+            "Span timing summary"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::tracing::{CaptureLayer, CapturedEvent};
+
+    #[test]
+    fn slow_spans_are_reported() {
+        let config = SpanTimingConfig::default()
+            .threshold("slow", Duration::from_millis(10))
+            .default_threshold(Duration::from_secs(60))
+            .summary_interval(None);
+        let (layer, reporter) = config.layer().unwrap();
+        assert!(reporter.is_none());
+        let capture = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(layer)
+            .with(capture.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("slow");
+            span.in_scope(|| std::thread::sleep(Duration::from_millis(15)));
+            std::thread::sleep(Duration::from_millis(5));
+            drop(span);
+
+            tracing::info_span!("fast").in_scope(|| {});
+        });
+
+        let events = capture.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].message.as_deref(), Some("Slow span"));
+        assert_eq!(events[0].field("span"), Some("slow"));
+        assert_eq!(events[0].field("threshold"), Some("10ms"));
+        let field = |event: &CapturedEvent, name| {
+            let value = event.field(name).unwrap();
+            let millis: f64 = value.strip_suffix("ms").unwrap().parse().unwrap();
+
+            millis
+        };
+        assert!(field(&events[0], "busy") >= 15.0);
+        assert!(field(&events[0], "idle") >= 5.0);
+    }
+
+    #[test]
+    fn summary_reports_percentiles() {
+        let samples = Samples::default();
+        let mut histogram = Histogram::default();
+        for millis in (1..=100).rev() {
+            histogram.record(Duration::from_millis(millis));
+        }
+        samples.lock().unwrap().insert("request", histogram);
+
+        let events = crate::tracing::capture_events(|| {
+            report_summary(&samples);
+            report_summary(&samples);
+        });
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].field("span"), Some("request"));
+        assert_eq!(events[0].field("count"), Some("100"));
+        assert_eq!(events[0].field("p50"), Some("49.283072ms"));
+        assert_eq!(events[0].field("p99"), Some("98.566144ms"));
+    }
+
+    #[test]
+    fn histogram_buckets_are_bounded() {
+        let mut histogram = Histogram::default();
+        for nanos in (0..64)
+            .map(|shift| 1u64 << shift)
+            .chain([0, 17, 1_000, u64::MAX])
+        {
+            histogram.record(Duration::from_nanos(nanos));
+
+            let midpoint = Histogram::midpoint(Histogram::index(nanos)).as_nanos() as f64;
+            assert!((midpoint - nanos as f64).abs() <= nanos as f64 / 16.0, "{nanos}");
+        }
+
+        assert!(histogram.buckets.len() < 1000);
+        assert_eq!(histogram.count, 68);
+        assert_eq!(histogram.percentile(0), Duration::ZERO);
+    }
+}